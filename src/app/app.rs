@@ -57,8 +57,13 @@ impl eframe::App for App {
 
             let (w, h) = self.state.img_cfg.resolution;
             ui.label(format!("resolution: {}x{}", w, h));
-            ui.label(format!("center: {}", self.state.img_cfg.center));
+            // 1pixelを区別できる桁数より少し多めに表示する
+            let digits = (-self.state.img_cfg.scale.log10()).max(0.0) as usize + 3;
+            ui.label(format!("center: {:.*}", digits, self.state.img_cfg.center));
             ui.label(format!("scale: {}", self.state.img_cfg.scale));
+            if let Some(backend) = self.state.engine.backend() {
+                ui.label(format!("backend: {}", backend));
+            }
 
             ui.label(format!("mode: {:?}", self.state.mode));
             ui.label(format!("recomp: {}", self.state.recomp));
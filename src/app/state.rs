@@ -1,5 +1,5 @@
-use num::Complex;
-use crate::{app::ui_render::RenderEngine, prelude::*};
+use num::{Complex, Zero};
+use crate::{app::ui_render::{AutoPrecisionEngine, RenderEngine}, prelude::*};
 
 pub struct AppState {
     pub img_cfg: ImageConfig,
@@ -18,7 +18,7 @@ pub struct AppState {
 #[derive(Debug, Clone)]
 pub struct ImageConfig {
     pub resolution: (usize, usize),
-    pub center: Complex<Float>,
+    pub center: Complex<ViewFloat>,  // 深い倍率でも中心を保てるよう多倍長で持つ
     pub scale: Float
 }
 
@@ -62,7 +62,7 @@ impl AppState {
 
     pub fn with_preset_values() -> Self {
         let resolution = RenderMode::Survey.resolusion();
-        let center = Complex::new(-0.5, 0.0);
+        let center = Complex::new(ViewFloat::from_f64(-0.5), ViewFloat::zero());
         let view_size = (3., 3.); //(resolution.0 as Float * scale, resolution.1 as Float * scale);
         let scale = view_size.0 / resolution.0 as Float;
         let img_cfg = ImageConfig {resolution, center, scale};
@@ -70,7 +70,7 @@ impl AppState {
         let max_iter = 300;
         let move_ratio = 0.1;
         let zoom_ratio = 0.5;
-        let dynamics = Mandelbrot::new();
+        let dynamics = Mandelbrot::new();  // 直接計算と摂動計算で同じものを使う
         let escape_radius = 2.0;
         let escape = EscapeByCount::new(max_iter, escape_radius);
         let palette = Palette::grayscale(256);
@@ -85,14 +85,18 @@ impl AppState {
             zoom_ratio,
             history: History { stack: Vec::new() },
             engine: Box::new(
-                EscapeTimeFractal::new(
-                    dynamics,
-                    escape,
-                    coloring,
-                    resolution,
-                    center,
-                    view_size
-                )
+                AutoPrecisionEngine::new(dynamics.clone(), escape, coloring.clone())
+                    .with_perturbation(Box::new(
+                        PerturbationFractal::new(
+                            dynamics,
+                            coloring,
+                            max_iter,
+                            escape_radius,
+                            resolution,
+                            center,
+                            view_size
+                        )
+                    ))
             ),
             rgba_buf: None,
         }
@@ -117,23 +121,28 @@ impl AppState {
     }
 
     pub fn move_left(&mut self) {
-        self.img_cfg.center.re -= (self.img_cfg.scale * self.img_cfg.resolution.0 as Float) * self.move_ratio;
+        let delta = (self.img_cfg.scale * self.img_cfg.resolution.0 as Float) * self.move_ratio;
+        self.img_cfg.center.re = self.img_cfg.center.re - ViewFloat::from_f64(delta);
     }
 
     pub fn move_right(&mut self) {
-        self.img_cfg.center.re += (self.img_cfg.scale * self.img_cfg.resolution.0 as Float) * self.move_ratio;
+        let delta = (self.img_cfg.scale * self.img_cfg.resolution.0 as Float) * self.move_ratio;
+        self.img_cfg.center.re = self.img_cfg.center.re + ViewFloat::from_f64(delta);
     }
 
     pub fn move_up(&mut self) {
-        self.img_cfg.center.im += (self.img_cfg.scale * self.img_cfg.resolution.1 as Float) * self.move_ratio;
+        let delta = (self.img_cfg.scale * self.img_cfg.resolution.1 as Float) * self.move_ratio;
+        self.img_cfg.center.im = self.img_cfg.center.im + ViewFloat::from_f64(delta);
     }
 
     pub fn move_down(&mut self) {
-        self.img_cfg.center.im -= (self.img_cfg.scale * self.img_cfg.resolution.1 as Float) * self.move_ratio;
+        let delta = (self.img_cfg.scale * self.img_cfg.resolution.1 as Float) * self.move_ratio;
+        self.img_cfg.center.im = self.img_cfg.center.im - ViewFloat::from_f64(delta);
     }
 
+    // 最も高い精度でも区別できない倍率までは拡大しない
     pub fn zoom_in(&mut self) {
-        self.img_cfg.scale *= self.zoom_ratio;
+        self.img_cfg.scale = (self.img_cfg.scale * self.zoom_ratio).max(Precision::min_scale());
     }
 
     pub fn zoom_out(&mut self) {
@@ -1,17 +1,46 @@
+use num::Complex;
 use crate::{app::state::ImageConfig, prelude::*};
 
 pub trait RenderEngine {
     // ラスタースキャン順でrgbargba...の順で
     fn compute(&mut self, img_cfg: &ImageConfig) -> Vec<u8>;
     fn compute_par(&mut self, img_cfg: &ImageConfig) -> Vec<u8>;
+    // 最後の描画に使った計算方法．分からなければNone
+    fn backend(&self) -> Option<Backend> {
+        None
+    }
 }
 
-impl<D, E, C> RenderEngine for EscapeTimeFractal<D, E, C>
+impl<D, E, C, T> RenderEngine for EscapeTimeFractal<D, E, C, T>
 where
-    D: ComplexDynamics + Sync + 'static,
-    E: EscapeEvaluator<D> + Sync + 'static,
+    T: Real,
+    D: StatefulDynamics<T> + Sync + 'static,
+    E: EscapeEvaluator<D, T> + Sync + 'static,
     C: Coloring<E::Output> + Sync + 'static,
     E::Output: Sync + Send,
+{
+    fn compute(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+        self.resolution = img_cfg.resolution;
+        self.center = Complex::new(img_cfg.center.re.to_real(), img_cfg.center.im.to_real());
+        self.view_size.0 = img_cfg.scale * img_cfg.resolution.0 as Float;
+        self.view_size.1 = img_cfg.scale * img_cfg.resolution.1 as Float;
+        let colors = self.colors();
+        self.rgba_buf_from_colors(&colors)
+    }
+    fn compute_par(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+        self.resolution = img_cfg.resolution;
+        self.center = Complex::new(img_cfg.center.re.to_real(), img_cfg.center.im.to_real());
+        self.view_size.0 = img_cfg.scale * img_cfg.resolution.0 as Float;
+        self.view_size.1 = img_cfg.scale * img_cfg.resolution.1 as Float;
+        let colors = self.colors_par();
+        self.rgba_buf_from_colors_par(&colors)
+    }
+}
+
+impl<D, C> RenderEngine for PerturbationFractal<D, C>
+where
+    D: PerturbableDynamics + Sync + 'static,
+    C: Coloring<EscapeResult> + Sync + 'static,
 {
     fn compute(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
         self.resolution = img_cfg.resolution;
@@ -19,8 +48,7 @@ where
         self.view_size.0 = img_cfg.scale * img_cfg.resolution.0 as Float;
         self.view_size.1 = img_cfg.scale * img_cfg.resolution.1 as Float;
         let values = self.escape_values();
-        let colors = self.colors_from_values(&values);
-        self.rgba_buf_from_colors(&colors)
+        rgba_buf_from_colors(&self.colors_from_values(&values))
     }
     fn compute_par(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
         self.resolution = img_cfg.resolution;
@@ -28,8 +56,136 @@ where
         self.view_size.0 = img_cfg.scale * img_cfg.resolution.0 as Float;
         self.view_size.1 = img_cfg.scale * img_cfg.resolution.1 as Float;
         let values = self.escape_values_par();
-        let colors = self.colors_from_values_par(&values);
-        self.rgba_buf_from_colors_par(&colors)
+        rgba_buf_from_colors(&self.colors_from_values_par(&values))
+    }
+    fn backend(&self) -> Option<Backend> {
+        Some(Backend::Perturbation(self.precision()))
+    }
+}
+
+fn rgba_buf_from_colors(colors: &[Color]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|c| [c.get_r(), c.get_g(), c.get_b(), c.get_a()])
+        .collect()
+}
+
+// 実数型Tで描画するengineに組み立てられる(dynamics, escape, coloring)の組
+pub trait EngineParts<T: Real> {
+    fn into_engine(self) -> Box<dyn RenderEngine>;
+}
+
+impl<T, D, E, C> EngineParts<T> for (D, E, C)
+where
+    T: Real,
+    D: StatefulDynamics<T> + Sync + 'static,
+    E: EscapeEvaluator<D, T> + Sync + 'static,
+    C: Coloring<E::Output> + Sync + 'static,
+    E::Output: Sync + Send,
+{
+    fn into_engine(self) -> Box<dyn RenderEngine> {
+        let (dynamics, escape, coloring) = self;
+        Box::new(EscapeTimeFractal::<D, E, C, T>::new(
+            dynamics,
+            escape,
+            coloring,
+            (1, 1),
+            Complex::new(T::zero(), T::zero()),
+            (1.0, 1.0),
+        ))
+    }
+}
+
+// ImageConfig::scaleから精度を選び，その精度のEscapeTimeFractalで描画する
+// f64で足りる倍率ではf64で，足りなければdouble-double，BigFloatの順に精度を上げる
+pub struct AutoPrecisionEngine {
+    f64: Box<dyn RenderEngine>,
+    double_double: Box<dyn RenderEngine>,
+    deep: DeepEngines,
+    backend: Option<Backend>,  // 最後に描画したengineが報告した方法．描画前はNone
+}
+
+// double-doubleでも足りない倍率で使うengine
+enum DeepEngines {
+    Direct {
+        big128: Box<dyn RenderEngine>,
+        big256: Box<dyn RenderEngine>,
+        big512: Box<dyn RenderEngine>,
+        big1024: Box<dyn RenderEngine>,
+    },
+    // BigFloatの直接反復は遅すぎるので，あればBigFloatの倍率はすべてこちらで描画する
+    Perturbation(Box<dyn RenderEngine>),
+}
+
+impl AutoPrecisionEngine {
+    pub fn new<D, E, C>(dynamics: D, escape: E, coloring: C) -> Self
+    where
+        (D, E, C): Clone
+            + EngineParts<Float>
+            + EngineParts<DoubleDouble>
+            + EngineParts<BigFloat<2>>
+            + EngineParts<BigFloat<4>>
+            + EngineParts<BigFloat<8>>
+            + EngineParts<BigFloat<16>>,
+    {
+        let parts = (dynamics, escape, coloring);
+        Self {
+            f64: EngineParts::<Float>::into_engine(parts.clone()),
+            double_double: EngineParts::<DoubleDouble>::into_engine(parts.clone()),
+            deep: DeepEngines::Direct {
+                big128: EngineParts::<BigFloat<2>>::into_engine(parts.clone()),
+                big256: EngineParts::<BigFloat<4>>::into_engine(parts.clone()),
+                big512: EngineParts::<BigFloat<8>>::into_engine(parts.clone()),
+                big1024: EngineParts::<BigFloat<16>>::into_engine(parts),
+            },
+            backend: None,
+        }
+    }
+
+    // 深い倍率で使う摂動論のengine(PerturbationFractalなど)を指定する
+    // double-doubleの倍率までは直接反復の方が速く，glitchもないのでそのまま使う
+    // BigFloatの直接反復のengineは使われなくなるので捨てる
+    pub fn with_perturbation(mut self, engine: Box<dyn RenderEngine>) -> Self {
+        self.deep = DeepEngines::Perturbation(engine);
+        self
+    }
+
+    pub fn precision(&self) -> Option<Precision> {
+        self.backend.map(|b| b.precision())
+    }
+
+    fn select(&mut self, precision: Precision) -> &mut Box<dyn RenderEngine> {
+        match (precision, &mut self.deep) {
+            (Precision::F64, _) => &mut self.f64,
+            (Precision::DoubleDouble, _) => &mut self.double_double,
+            (_, DeepEngines::Perturbation(engine)) => engine,
+            (Precision::Big128, DeepEngines::Direct { big128, .. }) => big128,
+            (Precision::Big256, DeepEngines::Direct { big256, .. }) => big256,
+            (Precision::Big512, DeepEngines::Direct { big512, .. }) => big512,
+            (Precision::Big1024, DeepEngines::Direct { big1024, .. }) => big1024,
+        }
+    }
+
+    fn render(&mut self, img_cfg: &ImageConfig, par: bool) -> Vec<u8> {
+        let precision = Precision::from_scale(img_cfg.scale);
+        let engine = self.select(precision);
+        let buf = if par { engine.compute_par(img_cfg) } else { engine.compute(img_cfg) };
+        // 摂動論のengineは参照軌道の精度を自分で決めるので，engineの報告を優先する
+        let backend = engine.backend().unwrap_or(Backend::Direct(precision));
+        self.backend = Some(backend);
+        buf
+    }
+}
+
+impl RenderEngine for AutoPrecisionEngine {
+    fn compute(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+        self.render(img_cfg, false)
+    }
+    fn compute_par(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+        self.render(img_cfg, true)
+    }
+    fn backend(&self) -> Option<Backend> {
+        self.backend
     }
 }
 
@@ -59,4 +215,72 @@ impl Renderer {
         });
     }
 }
-*/
\ No newline at end of file
+*/
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 描画せず，指定されたbackendを報告するだけのengine
+    struct Stub(Backend);
+
+    impl RenderEngine for Stub {
+        fn compute(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+            vec![0; img_cfg.resolution.0 * img_cfg.resolution.1 * 4]
+        }
+        fn compute_par(&mut self, img_cfg: &ImageConfig) -> Vec<u8> {
+            self.compute(img_cfg)
+        }
+        fn backend(&self) -> Option<Backend> {
+            Some(self.0)
+        }
+    }
+
+    fn engine() -> AutoPrecisionEngine {
+        let coloring = PaletteColoring::new(Palette::grayscale(16), 16);
+        AutoPrecisionEngine::new(Mandelbrot::new(), EscapeByCount::new(16, 2.0), coloring)
+    }
+
+    fn img_cfg(scale: Float) -> ImageConfig {
+        ImageConfig {
+            resolution: (2, 2),
+            center: Complex::new(ViewFloat::from_f64(-0.5), ViewFloat::from_f64(0.0)),
+            scale,
+        }
+    }
+
+    #[test]
+    fn backend_is_none_before_rendering() {
+        assert_eq!(engine().backend(), None);
+        assert_eq!(engine().precision(), None);
+    }
+
+    #[test]
+    fn direct_engines_are_used_without_perturbation() {
+        let mut engine = engine();
+        for scale in [1e-3, 1e-20, 1e-30, 1e-60] {
+            let precision = Precision::from_scale(scale);
+            assert_eq!(engine.compute(&img_cfg(scale)).len(), 16);
+            assert_eq!(engine.backend(), Some(Backend::Direct(precision)));
+        }
+    }
+
+    #[test]
+    fn perturbation_is_used_only_beyond_double_double() {
+        // 参照軌道の精度は倍率から選ぶ精度と違ってもよく，報告はengineに従う
+        let reported = Backend::Perturbation(Precision::Big512);
+        let mut engine = engine().with_perturbation(Box::new(Stub(reported)));
+
+        engine.compute(&img_cfg(1e-3));
+        assert_eq!(engine.backend(), Some(Backend::Direct(Precision::F64)));
+        engine.compute_par(&img_cfg(1e-20));
+        assert_eq!(engine.backend(), Some(Backend::Direct(Precision::DoubleDouble)));
+        assert_eq!(engine.precision(), Some(Precision::DoubleDouble));
+
+        for scale in [1e-30, 1e-60] {
+            engine.compute(&img_cfg(scale));
+            assert_eq!(engine.backend(), Some(reported));
+        }
+    }
+}
@@ -1,6 +1,9 @@
 pub mod complex_dynamics;
 pub mod complex_dynamics_presets;
 
+pub mod bailout;
+pub mod formula;
+
 pub mod escape_evaluator;
 pub mod escape_evaluator_presets;
 
@@ -8,3 +11,7 @@ pub mod coloring;
 pub mod coloring_presets;
 
 pub mod escape_time_fractal;
+pub mod supersampling;
+pub mod precision;
+pub mod perturbation;
+pub mod bla;
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::sync::Arc;
+
+use num_complex::{self, Complex};
+use crate::util::types::Float;
+
+// Productで軸に沿って発散する軌道を脱出とみなす|z|の下限
+const PRODUCT_AXIS_RADIUS: Float = 1e10;
+
+// 脱出判定の形状．閾値rはevaluatorのescape_radiusを使う
+#[derive(Clone)]
+pub enum Bailout {
+    Circle,  // |z| > r
+    Square,  // max(|Re z|, |Im z|) > r (Chebyshev距離)
+    Manhattan,  // |Re z| + |Im z| > r
+    RealPart,  // |Re z| > r
+    ImagPart,  // |Im z| > r
+    Product,  // |Re z| |Im z| > r^2．軸上では積が0なので，|z| > max(r^2, PRODUCT_AXIS_RADIUS)でも脱出とみなす
+    Custom(Arc<dyn Fn(Complex<Float>, Float) -> bool + Send + Sync>),  // f(z, r)がtrueなら脱出
+}
+
+impl Bailout {
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(Complex<Float>, Float) -> bool + Send + Sync + 'static,
+    {
+        Bailout::Custom(Arc::new(f))
+    }
+
+    pub fn escaped(&self, z: Complex<Float>, radius: Float) -> bool {
+        match self {
+            Bailout::Circle => z.norm_sqr() > radius * radius,
+            Bailout::Square => z.re.abs().max(z.im.abs()) > radius,
+            Bailout::Manhattan => z.re.abs() + z.im.abs() > radius,
+            Bailout::RealPart => z.re.abs() > radius,
+            Bailout::ImagPart => z.im.abs() > radius,
+            Bailout::Product => {
+                let r2 = radius * radius;
+                let axis = r2.max(PRODUCT_AXIS_RADIUS);
+                (z.re * z.im).abs() > r2 || z.norm_sqr() > axis * axis
+            }
+            Bailout::Custom(f) => f(z, radius),
+        }
+    }
+}
+
+// Customのクロージャは表示できないので名前だけ出す
+impl fmt::Debug for Bailout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bailout::Circle => write!(f, "Circle"),
+            Bailout::Square => write!(f, "Square"),
+            Bailout::Manhattan => write!(f, "Manhattan"),
+            Bailout::RealPart => write!(f, "RealPart"),
+            Bailout::ImagPart => write!(f, "ImagPart"),
+            Bailout::Product => write!(f, "Product"),
+            Bailout::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn product_escapes_along_the_axes() {
+        let product = Bailout::Product;
+        assert!(product.escaped(Complex::new(3.0, 3.0), 2.0));
+        assert!(!product.escaped(Complex::new(100.0, 0.01), 2.0));
+        assert!(!product.escaped(Complex::new(1e9, 0.0), 2.0));
+        assert!(product.escaped(Complex::new(0.0, -1e11), 2.0));
+
+        // 実軸上で発散するMandelbrotのc = 1: 0, 1, 2, 5, 26, 677, 458330, 2.1e11, …
+        let c = Complex::new(1.0, 0.0);
+        let mut z = Complex::new(0.0, 0.0);
+        let escape_iter = (1..=100).find(|_| {
+            z = z * z + c;
+            product.escaped(z, 2.0)
+        });
+        assert_eq!(escape_iter, Some(7));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::util::types::Float;
+
+use std::ops::{Add, Mul};
+use num_complex::Complex;
+
+// 実2次元の線形写像．複素数倍だけでなく共役やabsの場合分けも表せる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat2 {
+    pub m: [[Float; 2]; 2],
+}
+
+impl Mat2 {
+    pub const ZERO: Self = Self { m: [[0.0, 0.0], [0.0, 0.0]] };
+    pub const IDENTITY: Self = Self { m: [[1.0, 0.0], [0.0, 1.0]] };
+    pub const CONJ: Self = Self { m: [[1.0, 0.0], [0.0, -1.0]] };
+
+    pub fn new(m: [[Float; 2]; 2]) -> Self {
+        Self { m }
+    }
+
+    // 複素数aを掛ける写像
+    pub fn from_complex(a: Complex<Float>) -> Self {
+        Self { m: [[a.re, -a.im], [a.im, a.re]] }
+    }
+
+    pub fn apply(&self, v: Complex<Float>) -> Complex<Float> {
+        let [[a, b], [c, d]] = self.m;
+        Complex::new(a * v.re + b * v.im, c * v.re + d * v.im)
+    }
+
+    // 作用素ノルム(最大特異値)
+    pub fn norm(&self) -> Float {
+        let [[a, b], [c, d]] = self.m;
+        let f = a * a + b * b + c * c + d * d;
+        let det = a * d - b * c;
+        ((f + (f * f - 4.0 * det * det).max(0.0).sqrt()) * 0.5).sqrt()
+    }
+}
+
+impl Add for Mat2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (x, y) = (self.m, rhs.m);
+        Self { m: [
+            [x[0][0] + y[0][0], x[0][1] + y[0][1]],
+            [x[1][0] + y[1][0], x[1][1] + y[1][1]],
+        ] }
+    }
+}
+
+impl Mul for Mat2 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (x, y) = (self.m, rhs.m);
+        Self { m: [
+            [x[0][0] * y[0][0] + x[0][1] * y[1][0], x[0][0] * y[0][1] + x[0][1] * y[1][1]],
+            [x[1][0] * y[0][0] + x[1][1] * y[1][0], x[1][0] * y[0][1] + x[1][1] * y[1][1]],
+        ] }
+    }
+}
+
+
+// δ -> a δ + b dc で近似できる反復．|δ| < radiusの範囲で使える
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlaStep {
+    pub a: Mat2,
+    pub b: Mat2,
+    pub radius: Float,
+}
+
+impl BlaStep {
+    pub fn new(a: Mat2, b: Mat2, radius: Float) -> Self {
+        Self { a, b, radius }
+    }
+
+    pub fn apply(&self, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        self.a.apply(delta) + self.b.apply(dc)
+    }
+
+    // selfの後にnextを行う近似．dc_maxは描画範囲での|dc|の最大値
+    pub fn then(&self, next: &Self, dc_max: Float) -> Self {
+        let a_norm = self.a.norm();
+        // selfの後の|δ|がnext.radiusに収まる範囲
+        let next_radius = next.radius - self.b.norm() * dc_max;
+        let radius = if next_radius <= 0.0 {
+            0.0
+        } else if a_norm == 0.0 {
+            self.radius
+        } else {
+            self.radius.min(next_radius / a_norm)
+        };
+
+        Self {
+            a: next.a * self.a,
+            b: next.a * self.b + next.b,
+            radius,
+        }
+    }
+}
+
+
+// Bilinear Approximation．参照軌道の区間ごとの近似を2冪の長さで並べた表
+// levels[l][j]は参照軌道のZ_{1 + j 2^l}からの2^l回分の反復をまとめたもの
+// Z_0 = 0の周りでは線形近似が効かないので，Z_1から始める
+#[derive(Debug, Clone, Default)]
+pub struct BlaTable {
+    levels: Vec<Vec<BlaStep>>,
+}
+
+impl BlaTable {
+    // stepsはsteps[j]がZ_{j+1}からZ_{j+2}への1回分の近似．epsilonは線形近似で許す相対誤差
+    pub fn new(steps: Vec<BlaStep>, dc_max: Float, epsilon: Float) -> Self {
+        let base: Vec<BlaStep> = steps
+            .into_iter()
+            .map(|s| BlaStep { radius: s.radius * epsilon, ..s })
+            .collect();
+
+        let mut levels = vec![base];
+        while let Some(prev) = levels.last() {
+            if prev.len() < 2 {
+                break;
+            }
+            let next = prev
+                .chunks_exact(2)
+                .map(|pair| pair[0].then(&pair[1], dc_max))
+                .collect();
+            levels.push(next);
+        }
+
+        Self { levels }
+    }
+
+    // 参照軌道のZ_mから，|δ| = delta_normでlimit回以内に使える最長の近似とその反復回数
+    pub fn lookup(&self, m: usize, delta_norm: Float, limit: usize) -> Option<(&BlaStep, usize)> {
+        if m == 0 {
+            return None;
+        }
+        let k = m - 1;
+        // kが2^lで割り切れるlevelだけ見ればよい
+        let top = match k {
+            0 => self.levels.len(),
+            _ => (k.trailing_zeros() as usize + 1).min(self.levels.len()),
+        };
+
+        for (l, level) in self.levels[..top].iter().enumerate().rev() {
+            let len = 1 << l;
+            if len > limit {
+                continue;
+            }
+            match level.get(k >> l) {
+                Some(step) if delta_norm < step.radius => return Some((step, len)),
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composed_step_equals_sequential_steps() {
+        let first = BlaStep::new(
+            Mat2::new([[1.5, -0.25], [0.5, 2.0]]),
+            Mat2::from_complex(Complex::new(0.5, 1.0)),
+            1.0,
+        );
+        let second = BlaStep::new(Mat2::from_complex(Complex::new(-2.0, 0.5)), Mat2::CONJ, 1.0);
+        let (delta, dc) = (Complex::new(1e-3, -2e-3), Complex::new(-5e-4, 1e-4));
+
+        let composed = first.then(&second, 1e-3).apply(delta, dc);
+        let sequential = second.apply(first.apply(delta, dc), dc);
+        assert!((composed - sequential).norm() < 1e-15);
+    }
+
+    #[test]
+    fn composed_radius_keeps_the_next_step_valid() {
+        let first = BlaStep::new(Mat2::from_complex(Complex::new(2.0, 0.0)), Mat2::IDENTITY, 1.0);
+        let second = BlaStep::new(Mat2::IDENTITY, Mat2::IDENTITY, 1.0);
+        // |2δ + dc| < 1となるのは|δ| < (1 - 0.2) / 2
+        assert!((first.then(&second, 0.2).radius - 0.4).abs() < 1e-15);
+        // dcだけで次の範囲を超えるなら使えない
+        assert_eq!(first.then(&second, 2.0).radius, 0.0);
+    }
+
+    #[test]
+    fn lookup_picks_the_longest_aligned_step() {
+        let step = BlaStep::new(Mat2::IDENTITY, Mat2::ZERO, 1.0);
+        let table = BlaTable::new(vec![step; 8], 0.0, 1.0);
+
+        let len = |m, delta_norm, limit| table.lookup(m, delta_norm, limit).map(|(_, len)| len);
+        assert_eq!(len(0, 0.5, 100), None);
+        assert_eq!(len(1, 0.5, 100), Some(8));
+        assert_eq!(len(1, 0.5, 5), Some(4));
+        // Z_3からは2冪の境界に揃った長さ2までしか使えない
+        assert_eq!(len(3, 0.5, 100), Some(2));
+        assert_eq!(len(8, 0.5, 100), Some(1));
+        assert_eq!(len(9, 0.5, 100), None);
+        assert_eq!(len(1, 2.0, 100), None);
+    }
+}
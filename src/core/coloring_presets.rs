@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PaletteColoring {
     pub palette: Palette,
     pub max_iter: usize
@@ -22,8 +22,34 @@ impl Coloring<usize> for PaletteColoring {
     }
 }
 
+// smoothな反復回数用．隣り合うpaletteの色を補間してバンディングを消す
+impl Coloring<Float> for PaletteColoring {
+    fn color(&self, v: Float) -> Color {
+        let last = match self.palette.len() {
+            0 => return Color::BLACK,
+            n => n - 1,
+        };
 
-#[derive(Debug)]
+        let pos = (v * self.palette.len() as Float / self.max_iter as Float).max(0.0);
+        let idx = pos.floor() as usize;
+        if idx >= last {
+            return self.palette.back().copied().unwrap_or(Color::BLACK);
+        }
+
+        let c0 = self.palette.get(idx).copied().unwrap_or(Color::BLACK);
+        let c1 = self.palette.get(idx + 1).copied().unwrap_or(Color::BLACK);
+        c0.lerp(&c1, pos.fract() as f32)
+    }
+}
+
+impl Coloring<EscapeResult> for PaletteColoring {
+    fn color(&self, result: EscapeResult) -> Color {
+        self.color(result.iter)
+    }
+}
+
+
+#[derive(Debug, Clone)]
 pub struct HistogramColoring {
     cdf: Vec<Float>,
     palette: Palette,
@@ -59,3 +85,108 @@ impl Coloring<usize> for HistogramColoring {
             .unwrap_or(Color::BLACK)
     }
 }
+
+impl Coloring<EscapeResult> for HistogramColoring {
+    fn color(&self, result: EscapeResult) -> Color {
+        self.color(result.iter)
+    }
+}
+
+
+// 距離推定用．distanceがthickness以上の点はpaletteの最後の色になる
+#[derive(Debug, Clone)]
+pub struct DistanceColoring {
+    pub palette: Palette,
+    pub thickness: Float,
+}
+
+impl DistanceColoring {
+    pub fn new(palette: Palette, thickness: Float) -> Self {
+        Self { palette, thickness }
+    }
+}
+
+impl Coloring<Float> for DistanceColoring {
+    fn color(&self, distance: Float) -> Color {
+        let t = (distance / self.thickness).clamp(0.0, 1.0);
+        let idx = (t * (self.palette.len().max(1) - 1) as Float) as usize;
+        self.palette
+            .get(idx)
+            .copied()
+            .unwrap_or(Color::BLACK)
+    }
+}
+
+
+// orbit trap用．trapまでの距離がmax_distance以上の点はpaletteの最後の色になる
+#[derive(Debug, Clone)]
+pub struct TrapColoring {
+    pub palette: Palette,
+    pub max_distance: Float,
+}
+
+impl TrapColoring {
+    pub fn new(palette: Palette, max_distance: Float) -> Self {
+        Self { palette, max_distance }
+    }
+}
+
+impl Coloring<TrapResult> for TrapColoring {
+    fn color(&self, result: TrapResult) -> Color {
+        let t = (result.distance / self.max_distance).clamp(0.0, 1.0);
+        let idx = (t * (self.palette.len().max(1) - 1) as Float) as usize;
+        self.palette
+            .get(idx)
+            .copied()
+            .unwrap_or(Color::BLACK)
+    }
+}
+
+
+// 収束先の根ごとに色を変え，反復回数に応じて暗くする
+#[derive(Debug, Clone)]
+pub struct RootColoring {
+    pub colors: Vec<Color>,  // colors[i]がi番目の根の色
+    pub max_iter: usize,
+}
+
+impl RootColoring {
+    pub fn new(colors: Vec<Color>, max_iter: usize) -> Self {
+        Self { colors, max_iter }
+    }
+}
+
+impl Coloring<Convergence> for RootColoring {
+    fn color(&self, result: Convergence) -> Color {
+        let base = match result.root.and_then(|i| self.colors.get(i)) {
+            Some(&c) => c,
+            None => return Color::BLACK,
+        };
+        let t = result.iter as f32 / self.max_iter as f32;
+        base.lerp(&Color::BLACK, t)
+    }
+}
+
+impl Coloring<Convergence> for PaletteColoring {
+    fn color(&self, result: Convergence) -> Color {
+        self.color(result.iter)
+    }
+}
+
+impl Coloring<Termination> for RootColoring {
+    fn color(&self, result: Termination) -> Color {
+        match result {
+            Termination::Converged { root, iter } => self.color(Convergence { root, iter, converged: true }),
+            _ => Color::BLACK,
+        }
+    }
+}
+
+impl Coloring<Termination> for PaletteColoring {
+    fn color(&self, result: Termination) -> Color {
+        match result {
+            Termination::Escaped(iter) | Termination::Converged { iter, .. } => self.color(iter),
+            Termination::Bounded => self.color(self.max_iter),
+        }
+    }
+}
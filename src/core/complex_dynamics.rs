@@ -1,7 +1,98 @@
 use num_complex::{self, Complex};
+use crate::core::bailout::Bailout;
+use crate::util::real::Real;
 use crate::util::types::Float;
 
-pub trait ComplexDynamics {
-    fn initial_z(&self, c: Complex<Float>) -> Complex<Float>;
-    fn step(&self, z: Complex<Float>, c: Complex<Float>) -> Complex<Float>;
+// Tは反復に使う実数型．degreeなど反復の外で使う値はFloatのまま
+pub trait ComplexDynamics<T: Real = Float> {
+    fn initial_z(&self, c: Complex<T>) -> Complex<T>;
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T>;
+
+    // 遠方でのstepの次数(z^d + ...のd)．smoothな反復回数の計算に使う
+    fn degree(&self) -> Float {
+        2.0
+    }
+
+    // 反復しなくても軌道が|z| <= 2に留まると分かる点ならtrue
+    // EscapeByCountなどは，脱出判定が半径2以上の円のときだけこれで反復を省略する
+    fn is_interior(&self, _c: Complex<T>) -> bool {
+        false
+    }
+
+    // 脱出判定の形状．超越関数の力学系などで円以外を使う
+    // evaluatorはpixelごとに呼ぶので，Customのクロージャを複製しないよう参照を返す
+    fn bailout(&self) -> &Bailout {
+        &Bailout::Circle
+    }
+}
+
+// 導関数を追跡できる力学系．距離推定(distance estimation)に使う
+// Mandelbrot型ではdz/dc，Julia型ではdz/dz0を追跡する
+pub trait DifferentiableDynamics<T: Real = Float>: ComplexDynamics<T> {
+    fn initial_dz(&self, c: Complex<T>) -> Complex<T>;
+    // zはstep前の値
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, c: Complex<T>) -> Complex<T>;
+}
+
+// 過去の反復値などの状態を持つ力学系(Phoenixなど)
+// ComplexDynamicsはState = Complex<T>のStatefulDynamicsとして扱われる
+pub trait StatefulDynamics<T: Real = Float> {
+    type State: Copy;
+
+    fn init_state(&self, c: Complex<T>) -> Self::State;
+    fn step_state(&self, state: Self::State, c: Complex<T>) -> Self::State;
+    // stateの現在のz
+    fn state_z(&self, state: &Self::State) -> Complex<T>;
+
+    fn state_norm_sqr(&self, state: &Self::State) -> Float {
+        self.state_z(state).norm_sqr().to_f64()
+    }
+
+    // 周期検出に使うstate間の距離の2乗
+    fn state_distance_sqr(&self, a: &Self::State, b: &Self::State) -> Float {
+        (self.state_z(a) - self.state_z(b)).norm_sqr().to_f64()
+    }
+
+    // ComplexDynamicsのdegree, is_interior, bailoutに当たるもの
+    // blanket implで両方のtraitが実装される型でメソッド呼び出しが曖昧にならないようstate_を付ける
+    fn state_degree(&self) -> Float {
+        2.0
+    }
+
+    fn state_is_interior(&self, _c: Complex<T>) -> bool {
+        false
+    }
+
+    fn state_bailout(&self) -> &Bailout {
+        &Bailout::Circle
+    }
+}
+
+impl<T: Real, D: ComplexDynamics<T>> StatefulDynamics<T> for D {
+    type State = Complex<T>;
+
+    fn init_state(&self, c: Complex<T>) -> Complex<T> {
+        self.initial_z(c)
+    }
+    fn step_state(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        self.step(z, c)
+    }
+    fn state_z(&self, z: &Complex<T>) -> Complex<T> {
+        *z
+    }
+    fn state_degree(&self) -> Float {
+        self.degree()
+    }
+    fn state_is_interior(&self, c: Complex<T>) -> bool {
+        self.is_interior(c)
+    }
+    fn state_bailout(&self) -> &Bailout {
+        self.bailout()
+    }
+}
+
+// 既知の吸引的な不動点(Newton法の根など)を持つ力学系．収束判定型のevaluatorで使う
+// 不動点は収束先の同定にだけ使うのでFloatで持つ
+pub trait ConvergentDynamics<T: Real = Float>: StatefulDynamics<T> {
+    fn attractors(&self) -> &[Complex<Float>];
 }
@@ -1,7 +1,8 @@
+use num::traits::{One, Zero};
 use num_complex::{self, Complex};
 use crate::prelude::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mandelbrot;
 
 impl Mandelbrot {
@@ -10,38 +11,83 @@ impl Mandelbrot {
     }
 }
 
-impl ComplexDynamics for Mandelbrot {
-    fn initial_z(&self, _c: Complex<Float>) -> Complex<Float> {
-        Complex::ZERO
+impl<T: Real> ComplexDynamics<T> for Mandelbrot {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
     }
-    fn step(&self, z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
         z * z + c
     }
+
+    // main cardioidとperiod-2 bulbの内部判定
+    fn is_interior(&self, c: Complex<T>) -> bool {
+        let quarter = T::from_f64(0.25);
+        let x = c.re - quarter;
+        let y2 = c.im * c.im;
+        let q = x * x + y2;
+        if q * (q + x) <= quarter * y2 {
+            return true;
+        }
+
+        let x = c.re + T::one();
+        x * x + y2 <= T::from_f64(0.0625)
+    }
+}
+
+impl<T: Real> DifferentiableDynamics<T> for Mandelbrot {
+    fn initial_dz(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _c: Complex<T>) -> Complex<T> {
+        z * dz * T::from_f64(2.0) + Complex::one()
+    }
 }
 
+// (Z + δ)^2 - Z^2 = (2Z + δ)δ
+impl PerturbableDynamics for Mandelbrot {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        (2.0 * z_ref + delta) * delta + dc
+    }
 
-#[derive(Debug)]
-pub struct Julia {
-    pub c: Complex<Float>,
+    // δ^2を無視する．|δ| << |2Z|で使える
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        Some(BlaStep::new(Mat2::from_complex(2.0 * z_ref), Mat2::IDENTITY, 2.0 * z_ref.norm()))
+    }
 }
 
-impl Julia {
-    pub fn new(c: Complex<Float>) -> Self {
+
+#[derive(Debug, Clone)]
+pub struct Julia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> Julia<T> {
+    pub fn new(c: Complex<T>) -> Self {
         Self { c }
     }
 }
 
-impl ComplexDynamics for Julia {
-    fn initial_z(&self, z: Complex<Float>) -> Complex<Float> {
+impl<T: Real> ComplexDynamics<T> for Julia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
         z
     }
 
-    fn step(&self, z: Complex<Float>, _: Complex<Float>) -> Complex<Float> {
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
         z * z + self.c
     }
 }
 
+impl<T: Real> DifferentiableDynamics<T> for Julia<T> {
+    fn initial_dz(&self, _z: Complex<T>) -> Complex<T> {
+        Complex::one()
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _: Complex<T>) -> Complex<T> {
+        z * dz * T::from_f64(2.0)
+    }
+}
+
 
+#[derive(Debug, Clone)]
 pub struct BurningShip;
 
 impl BurningShip {
@@ -50,12 +96,1051 @@ impl BurningShip {
     }
 }
 
-impl ComplexDynamics for BurningShip {
-    fn initial_z(&self, _c: Complex<Float>) -> Complex<Float> {
-        Complex::ZERO
+impl<T: Real> ComplexDynamics<T> for BurningShip {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let z = Complex::new(z.re.abs(), z.im.abs());
+        z * z + c
+    }
+}
+
+// absで折り返した後のzとdzに対する微分．折り返しは各成分の符号反転として扱う
+// absを含むstepは正則でなく本来のJacobianは実2x2行列なので，dzを複素数で追跡するこの微分は近似．
+// 距離推定は目安として使い，軸の近くでは誤差が大きくなる
+impl<T: Real> DifferentiableDynamics<T> for BurningShip {
+    fn initial_dz(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _c: Complex<T>) -> Complex<T> {
+        let (sx, sy) = (z.re.signum(), z.im.signum());
+        let z = Complex::new(z.re.abs(), z.im.abs());
+        let dz = Complex::new(sx * dz.re, sy * dz.im);
+        z * dz * T::from_f64(2.0) + Complex::one()
+    }
+}
+
+// |Re z|，|Im z|の符号の場合分けでδを求める
+impl PerturbableDynamics for BurningShip {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let (x, y) = (delta.re, delta.im);
+        let re = (2.0 * zx + x) * x - (2.0 * zy + y) * y;
+        let im = 2.0 * diffabs(zx * zy, zx * y + x * zy + x * y);
+        Complex::new(re, im) + dc
     }
-    fn step(&self, z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+
+    // δで|Re z|，|Im z|の符号が変わらない範囲で線形化する
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let (sx, sy) = (zx.signum(), zy.signum());
+        let a = Mat2::new([
+            [2.0 * zx, -2.0 * zy],
+            [2.0 * sx * zy.abs(), 2.0 * sy * zx.abs()],
+        ]);
+        let radius = (2.0 * z_ref.norm()).min(zx.abs()).min(zy.abs());
+        Some(BlaStep::new(a, Mat2::IDENTITY, radius))
+    }
+}
+
+
+// (|Re z| + i|Im z|)^2 + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct BurningShipJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> BurningShipJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for BurningShipJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
         let z = Complex::new(z.re.abs(), z.im.abs());
+        z * z + self.c
+    }
+}
+
+
+// conj(z)^2 + c (Mandelbar)
+#[derive(Debug, Clone, Default)]
+pub struct Tricorn;
+
+impl Tricorn {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Tricorn {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let z = z.conj();
         z * z + c
     }
-}
\ No newline at end of file
+}
+
+// conj(Z + δ)^2 - conj(Z)^2 = conj((2Z + δ)δ)
+impl PerturbableDynamics for Tricorn {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        ((2.0 * z_ref + delta) * delta).conj() + dc
+    }
+
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        Some(BlaStep::new(Mat2::CONJ * Mat2::from_complex(2.0 * z_ref), Mat2::IDENTITY, 2.0 * z_ref.norm()))
+    }
+}
+
+
+// conj(z)^2 + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct TricornJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> TricornJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for TricornJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        let z = z.conj();
+        z * z + self.c
+    }
+}
+
+
+// |Re(z^2)| + i Im(z^2) + c
+#[derive(Debug, Clone, Default)]
+pub struct Celtic;
+
+impl Celtic {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Celtic {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let z = z * z;
+        Complex::new(z.re.abs(), z.im) + c
+    }
+}
+
+impl PerturbableDynamics for Celtic {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        let z2 = z_ref * z_ref;
+        let d2 = (2.0 * z_ref + delta) * delta;
+        Complex::new(diffabs(z2.re, d2.re), d2.im) + dc
+    }
+
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        let z2 = z_ref * z_ref;
+        let s = z2.re.signum();
+        let a = Mat2::new([[s, 0.0], [0.0, 1.0]]) * Mat2::from_complex(2.0 * z_ref);
+        let radius = fold_radius(z_ref, &[z2.re]);
+        Some(BlaStep::new(a, Mat2::IDENTITY, radius))
+    }
+}
+
+
+// |Re(z^2)| + i Im(z^2) + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct CelticJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> CelticJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for CelticJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        let z = z * z;
+        Complex::new(z.re.abs(), z.im) + self.c
+    }
+}
+
+
+// |Re(z^2)| + i|Im(z^2)| + c
+#[derive(Debug, Clone, Default)]
+pub struct Buffalo;
+
+impl Buffalo {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Buffalo {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let z = z * z;
+        Complex::new(z.re.abs(), z.im.abs()) + c
+    }
+}
+
+impl PerturbableDynamics for Buffalo {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        let z2 = z_ref * z_ref;
+        let d2 = (2.0 * z_ref + delta) * delta;
+        Complex::new(diffabs(z2.re, d2.re), diffabs(z2.im, d2.im)) + dc
+    }
+
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        let z2 = z_ref * z_ref;
+        let (sx, sy) = (z2.re.signum(), z2.im.signum());
+        let a = Mat2::new([[sx, 0.0], [0.0, sy]]) * Mat2::from_complex(2.0 * z_ref);
+        let radius = fold_radius(z_ref, &[z2.re, z2.im]);
+        Some(BlaStep::new(a, Mat2::IDENTITY, radius))
+    }
+}
+
+
+// |Re(z^2)| + i|Im(z^2)| + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct BuffaloJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> BuffaloJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for BuffaloJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        let z = z * z;
+        Complex::new(z.re.abs(), z.im.abs()) + self.c
+    }
+}
+
+
+// (x^2 - y^2) - 2i|x|y + c
+#[derive(Debug, Clone, Default)]
+pub struct PerpendicularMandelbrot;
+
+impl PerpendicularMandelbrot {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for PerpendicularMandelbrot {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        Complex::new(z.re * z.re - z.im * z.im, -(z.re.abs() * z.im * T::from_f64(2.0))) + c
+    }
+}
+
+impl PerturbableDynamics for PerpendicularMandelbrot {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let (x, y) = (delta.re, delta.im);
+        let re = (2.0 * zx + x) * x - (2.0 * zy + y) * y;
+        // |X + x|(Y + y) - |X|Y = |X + x|y + (|X + x| - |X|)Y
+        let im = -2.0 * ((zx + x).abs() * y + diffabs(zx, x) * zy);
+        Complex::new(re, im) + dc
+    }
+
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let a = Mat2::new([
+            [2.0 * zx, -2.0 * zy],
+            [-2.0 * zx.signum() * zy, -2.0 * zx.abs()],
+        ]);
+        let radius = (2.0 * z_ref.norm()).min(zx.abs());
+        Some(BlaStep::new(a, Mat2::IDENTITY, radius))
+    }
+}
+
+
+// (x^2 - y^2) - 2i|x|y + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct PerpendicularMandelbrotJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> PerpendicularMandelbrotJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for PerpendicularMandelbrotJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        Complex::new(z.re * z.re - z.im * z.im, -(z.re.abs() * z.im * T::from_f64(2.0))) + self.c
+    }
+}
+
+
+// (x^2 - y^2) - 2ix|y| + c
+#[derive(Debug, Clone, Default)]
+pub struct PerpendicularBurningShip;
+
+impl PerpendicularBurningShip {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for PerpendicularBurningShip {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        Complex::new(z.re * z.re - z.im * z.im, -(z.re * z.im.abs() * T::from_f64(2.0))) + c
+    }
+}
+
+impl PerturbableDynamics for PerpendicularBurningShip {
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let (x, y) = (delta.re, delta.im);
+        let re = (2.0 * zx + x) * x - (2.0 * zy + y) * y;
+        // (X + x)|Y + y| - X|Y| = x|Y + y| + X(|Y + y| - |Y|)
+        let im = -2.0 * (x * (zy + y).abs() + zx * diffabs(zy, y));
+        Complex::new(re, im) + dc
+    }
+
+    fn linearize(&self, z_ref: Complex<Float>) -> Option<BlaStep> {
+        let (zx, zy) = (z_ref.re, z_ref.im);
+        let a = Mat2::new([
+            [2.0 * zx, -2.0 * zy],
+            [-2.0 * zy.abs(), -2.0 * zy.signum() * zx],
+        ]);
+        let radius = (2.0 * z_ref.norm()).min(zy.abs());
+        Some(BlaStep::new(a, Mat2::IDENTITY, radius))
+    }
+}
+
+
+// (x^2 - y^2) - 2ix|y| + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct PerpendicularBurningShipJulia<T: Real = Float> {
+    pub c: Complex<T>,
+}
+
+impl<T: Real> PerpendicularBurningShipJulia<T> {
+    pub fn new(c: Complex<T>) -> Self {
+        Self { c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for PerpendicularBurningShipJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        Complex::new(z.re * z.re - z.im * z.im, -(z.re * z.im.abs() * T::from_f64(2.0))) + self.c
+    }
+}
+
+
+// z^power + c．powerが0以下のときは0の負冪を避けるためz0 = cから始める
+#[derive(Debug, Clone)]
+pub struct Multibrot {
+    pub power: i32,
+}
+
+impl Multibrot {
+    pub fn new(power: i32) -> Self {
+        Self { power }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Multibrot {
+    fn initial_z(&self, c: Complex<T>) -> Complex<T> {
+        if self.power > 0 { Complex::zero() } else { c }
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        z.powi(self.power) + c
+    }
+    fn degree(&self) -> Float {
+        self.power as Float
+    }
+}
+
+impl<T: Real> DifferentiableDynamics<T> for Multibrot {
+    fn initial_dz(&self, _c: Complex<T>) -> Complex<T> {
+        if self.power > 0 { Complex::zero() } else { Complex::one() }
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _c: Complex<T>) -> Complex<T> {
+        z.powi(self.power - 1) * dz * T::from_f64(self.power as Float) + Complex::one()
+    }
+}
+
+
+// z^power + c (powerは実数)．powerが0以下のときはz0 = cから始める
+#[derive(Debug, Clone)]
+pub struct MultibrotReal {
+    pub power: Float,
+}
+
+impl MultibrotReal {
+    pub fn new(power: Float) -> Self {
+        Self { power }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for MultibrotReal {
+    fn initial_z(&self, c: Complex<T>) -> Complex<T> {
+        if self.power > 0.0 { Complex::zero() } else { c }
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        complex_powf(z, self.power) + c
+    }
+    fn degree(&self) -> Float {
+        self.power
+    }
+}
+
+impl<T: Real> DifferentiableDynamics<T> for MultibrotReal {
+    fn initial_dz(&self, _c: Complex<T>) -> Complex<T> {
+        if self.power > 0.0 { Complex::zero() } else { Complex::one() }
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _c: Complex<T>) -> Complex<T> {
+        complex_powf(z, self.power - 1.0) * dz * T::from_f64(self.power) + Complex::one()
+    }
+}
+
+
+// z^power + c (cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct Multijulia<T: Real = Float> {
+    pub power: i32,
+    pub c: Complex<T>,
+}
+
+impl<T: Real> Multijulia<T> {
+    pub fn new(power: i32, c: Complex<T>) -> Self {
+        Self { power, c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Multijulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        z.powi(self.power) + self.c
+    }
+    fn degree(&self) -> Float {
+        self.power as Float
+    }
+}
+
+impl<T: Real> DifferentiableDynamics<T> for Multijulia<T> {
+    fn initial_dz(&self, _z: Complex<T>) -> Complex<T> {
+        Complex::one()
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _: Complex<T>) -> Complex<T> {
+        z.powi(self.power - 1) * dz * T::from_f64(self.power as Float)
+    }
+}
+
+
+// z^power + c (powerは実数，cは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct MultijuliaReal<T: Real = Float> {
+    pub power: Float,
+    pub c: Complex<T>,
+}
+
+impl<T: Real> MultijuliaReal<T> {
+    pub fn new(power: Float, c: Complex<T>) -> Self {
+        Self { power, c }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for MultijuliaReal<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        complex_powf(z, self.power) + self.c
+    }
+    fn degree(&self) -> Float {
+        self.power
+    }
+}
+
+impl<T: Real> DifferentiableDynamics<T> for MultijuliaReal<T> {
+    fn initial_dz(&self, _z: Complex<T>) -> Complex<T> {
+        Complex::one()
+    }
+    fn step_dz(&self, z: Complex<T>, dz: Complex<T>, _: Complex<T>) -> Complex<T> {
+        complex_powf(z, self.power - 1.0) * dz * T::from_f64(self.power)
+    }
+}
+
+
+// StatefulDynamicsを直接実装する型(Phoenix, Hybrid)は，ComplexDynamicsからのblanket implと
+// 重ならないよう，このcrateのReal型ごとに具体的なimplを作る
+// impl<T: Real>にすると，下流crateが独自のRealに対してComplexDynamicsを実装できてしまい衝突する
+macro_rules! impl_for_reals {
+    ($impl_for:ident) => {
+        $impl_for!([] f32);
+        $impl_for!([] f64);
+        $impl_for!([] DoubleDouble);
+        $impl_for!([] QuadDouble);
+        $impl_for!([const N: usize] BigFloat<N>);
+    };
+}
+
+
+// Phoenix: z_{n+1} = z_n^2 + c + p z_{n-1} (c, pは固定，z0が描画点，z_{-1} = 0)
+#[derive(Debug, Clone)]
+pub struct Phoenix<T: Real = Float> {
+    pub c: Complex<T>,
+    pub p: Complex<T>,
+}
+
+impl<T: Real> Phoenix<T> {
+    pub fn new(c: Complex<T>, p: Complex<T>) -> Self {
+        Self { c, p }
+    }
+}
+
+// stateは(z_n, z_{n-1})
+macro_rules! impl_phoenix {
+    ([$($generics:tt)*] $t:ty) => {
+        impl<$($generics)*> StatefulDynamics<$t> for Phoenix<$t> {
+            type State = (Complex<$t>, Complex<$t>);
+
+            fn init_state(&self, z: Complex<$t>) -> Self::State {
+                (z, Complex::zero())
+            }
+            fn step_state(&self, (z, z_prev): Self::State, _: Complex<$t>) -> Self::State {
+                (z * z + self.c + self.p * z_prev, z)
+            }
+            fn state_z(&self, (z, _): &Self::State) -> Complex<$t> {
+                *z
+            }
+            fn state_distance_sqr(&self, (z0, z0_prev): &Self::State, (z1, z1_prev): &Self::State) -> Float {
+                ((z0 - z1).norm_sqr() + (z0_prev - z1_prev).norm_sqr()).to_f64()
+            }
+        }
+    };
+}
+
+impl_for_reals!(impl_phoenix);
+
+
+// 多項式pに対するNewton法: z - p(z) / p'(z) (z0が描画点)
+#[derive(Debug, Clone)]
+pub struct Newton {
+    pub polynomial: Polynomial,
+    roots: Vec<Complex<Float>>,
+}
+
+impl Newton {
+    pub fn new(polynomial: Polynomial) -> Self {
+        let roots = polynomial.roots();
+        Self { polynomial, roots }
+    }
+
+    pub fn from_roots(roots: Vec<Complex<Float>>) -> Self {
+        let polynomial = Polynomial::from_roots(&roots);
+        Self { polynomial, roots }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Newton {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        let (p, dp) = self.polynomial.eval_with_derivative(z);
+        z - p / dp
+    }
+}
+
+impl<T: Real> ConvergentDynamics<T> for Newton {
+    fn attractors(&self) -> &[Complex<Float>] {
+        &self.roots
+    }
+}
+
+
+// Nova: z - relaxation * p(z) / p'(z) + c (z0は固定，cが描画点)
+// 古典的なNovaはp(z) = z^3 - 1，relaxation = 1，z0 = 1
+#[derive(Debug, Clone)]
+pub struct Nova<T: Real = Float> {
+    pub polynomial: Polynomial,
+    pub relaxation: T,
+    pub z0: Complex<T>,
+}
+
+impl<T: Real> Nova<T> {
+    pub fn new(polynomial: Polynomial, relaxation: T, z0: Complex<T>) -> Self {
+        Self { polynomial, relaxation, z0 }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Nova<T> {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        self.z0
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let (p, dp) = self.polynomial.eval_with_derivative(z);
+        z - p / dp * self.relaxation + c
+    }
+}
+
+// 不動点はcに依存するので既知のattractorはない．収束は|z_{n+1} - z_n|で判定する
+impl<T: Real> ConvergentDynamics<T> for Nova<T> {
+    fn attractors(&self) -> &[Complex<Float>] {
+        &[]
+    }
+}
+
+
+// z = 1はcによらず吸引的な不動点になりうる
+const MAGNET_ATTRACTORS: [Complex<Float>; 1] = [Complex::ONE];
+
+// Magnet type I: ((z^2 + c - 1) / (2z + c - 2))^2
+#[derive(Debug, Clone, Default)]
+pub struct Magnet1;
+
+impl Magnet1 {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Magnet1 {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let two = T::from_f64(2.0);
+        let w = (z * z + c - T::one()) / (z * two + c - two);
+        w * w
+    }
+}
+
+impl<T: Real> ConvergentDynamics<T> for Magnet1 {
+    fn attractors(&self) -> &[Complex<Float>] {
+        &MAGNET_ATTRACTORS
+    }
+}
+
+
+// Magnet type II: ((z^3 + 3(c - 1)z + (c - 1)(c - 2)) / (3z^2 + 3(c - 2)z + (c - 1)(c - 2) + 1))^2
+#[derive(Debug, Clone, Default)]
+pub struct Magnet2;
+
+impl Magnet2 {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for Magnet2 {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        let three = T::from_f64(3.0);
+        let c1 = c - T::one();
+        let c2 = c - T::from_f64(2.0);
+        let num = z * z * z + c1 * z * three + c1 * c2;
+        let den = z * z * three + c2 * z * three + c1 * c2 + T::one();
+        let w = num / den;
+        w * w
+    }
+}
+
+impl<T: Real> ConvergentDynamics<T> for Magnet2 {
+    fn attractors(&self) -> &[Complex<Float>] {
+        &MAGNET_ATTRACTORS
+    }
+}
+
+
+// 超越関数の力学系．軌道は実部(exp)や虚部(sin, cos)方向に発散するので，
+// bailoutも円ではなく|Re z|や|Im z|で判定する．escape_radiusは50程度を想定
+
+// λ e^z (λは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct ExpJulia<T: Real = Float> {
+    pub lambda: Complex<T>,
+}
+
+impl<T: Real> ExpJulia<T> {
+    pub fn new(lambda: Complex<T>) -> Self {
+        Self { lambda }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for ExpJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        self.lambda * complex_exp(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::RealPart
+    }
+}
+
+
+// c e^z (z0 = 0: 漸近値)
+#[derive(Debug, Clone, Default)]
+pub struct ExpMandelbrot;
+
+impl ExpMandelbrot {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for ExpMandelbrot {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        c * complex_exp(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::RealPart
+    }
+}
+
+
+// k sin z (kは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct SinJulia<T: Real = Float> {
+    pub k: Complex<T>,
+}
+
+impl<T: Real> SinJulia<T> {
+    pub fn new(k: Complex<T>) -> Self {
+        Self { k }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for SinJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        self.k * complex_sin(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::ImagPart
+    }
+}
+
+
+// c sin z (z0 = π/2: 臨界点)
+#[derive(Debug, Clone, Default)]
+pub struct SinMandelbrot;
+
+impl SinMandelbrot {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for SinMandelbrot {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::new(T::pi() * T::from_f64(0.5), T::zero())
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        c * complex_sin(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::ImagPart
+    }
+}
+
+
+// k cos z (kは固定，z0が描画点)
+#[derive(Debug, Clone)]
+pub struct CosJulia<T: Real = Float> {
+    pub k: Complex<T>,
+}
+
+impl<T: Real> CosJulia<T> {
+    pub fn new(k: Complex<T>) -> Self {
+        Self { k }
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for CosJulia<T> {
+    fn initial_z(&self, z: Complex<T>) -> Complex<T> {
+        z
+    }
+    fn step(&self, z: Complex<T>, _: Complex<T>) -> Complex<T> {
+        self.k * complex_cos(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::ImagPart
+    }
+}
+
+
+// c cos z (z0 = 0: 臨界点)
+#[derive(Debug, Clone, Default)]
+pub struct CosMandelbrot;
+
+impl CosMandelbrot {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<T: Real> ComplexDynamics<T> for CosMandelbrot {
+    fn initial_z(&self, _c: Complex<T>) -> Complex<T> {
+        Complex::zero()
+    }
+    fn step(&self, z: Complex<T>, c: Complex<T>) -> Complex<T> {
+        c * complex_cos(z)
+    }
+    fn bailout(&self) -> &Bailout {
+        &Bailout::ImagPart
+    }
+}
+
+
+// 複数のdynamicsを反復ごとに順に切り替えるhybrid
+// 例: Hybrid::new(Mandelbrot::new(), 2).then(BurningShip::new(), 1)
+//     はMandelbrotを2回，BurningShipを1回…と繰り返す
+pub struct Hybrid<T: Real = Float> {
+    stages: Vec<Box<dyn ComplexDynamics<T> + Send + Sync>>,
+    schedule: Vec<usize>,  // 周期内の位置 -> stagesのindex
+}
+
+impl<T: Real> Hybrid<T> {
+    // 初期値z0は最初のdynamicsのinitial_zを使う．repeatは1以上
+    pub fn new<D>(dynamics: D, repeat: usize) -> Self
+    where
+        D: ComplexDynamics<T> + Send + Sync + 'static,
+    {
+        Self { stages: Vec::new(), schedule: Vec::new() }.then(dynamics, repeat)
+    }
+
+    pub fn then<D>(mut self, dynamics: D, repeat: usize) -> Self
+    where
+        D: ComplexDynamics<T> + Send + Sync + 'static,
+    {
+        assert!(repeat >= 1);
+
+        let idx = self.stages.len();
+        self.stages.push(Box::new(dynamics));
+        self.schedule.extend(std::iter::repeat_n(idx, repeat));
+        self
+    }
+}
+
+// stateは(z, 周期内の位置)
+macro_rules! impl_hybrid {
+    ([$($generics:tt)*] $t:ty) => {
+        impl<$($generics)*> StatefulDynamics<$t> for Hybrid<$t> {
+            type State = (Complex<$t>, usize);
+
+            fn init_state(&self, c: Complex<$t>) -> Self::State {
+                (self.stages[0].initial_z(c), 0)
+            }
+            fn step_state(&self, (z, pos): Self::State, c: Complex<$t>) -> Self::State {
+                let z = self.stages[self.schedule[pos]].step(z, c);
+                (z, (pos + 1) % self.schedule.len())
+            }
+            fn state_z(&self, (z, _): &Self::State) -> Complex<$t> {
+                *z
+            }
+            // 周期内の位置が違えば同じstateではない
+            fn state_distance_sqr(&self, (z0, pos0): &Self::State, (z1, pos1): &Self::State) -> Float {
+                if pos0 != pos1 {
+                    return Float::INFINITY;
+                }
+                (z0 - z1).norm_sqr().to_f64()
+            }
+            // 1反復あたりの次数として，1周期分の次数の相乗平均を使う
+            fn state_degree(&self) -> Float {
+                let log_sum: Float = self.schedule
+                    .iter()
+                    .map(|&i| self.stages[i].degree().ln())
+                    .sum();
+                (log_sum / self.schedule.len() as Float).exp()
+            }
+            fn state_bailout(&self) -> &Bailout {
+                self.stages[0].bailout()
+            }
+        }
+    };
+}
+
+impl_for_reals!(impl_hybrid);
+
+
+// z^2の成分folded(の絶対値)で折り返す式を線形化できる|δ|の目安
+// z^2の変化2Zδでfoldedの符号が変わらず，δ^2も無視できる範囲
+fn fold_radius(z_ref: Complex<Float>, folded: &[Float]) -> Float {
+    let dz = 2.0 * z_ref.norm();
+    if dz == 0.0 {
+        return 0.0;
+    }
+    folded
+        .iter()
+        .fold(dz, |r, f| r.min(f.abs() / dz))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 拡張精度でもf64と同じ軌道を(f64の精度で)たどる
+    // パラメータを持つdynamicsはf64版とT版を別に作って渡す
+    fn assert_same_orbit<T, D, E>(dynamics: &D, extended: &E, c: Complex<Float>)
+    where
+        T: Real,
+        D: StatefulDynamics,
+        E: StatefulDynamics<T>,
+    {
+        let mut state = dynamics.init_state(c);
+        let mut state_ext = extended.init_state(complex_from_f64(c));
+        for _ in 0..10 {
+            state = dynamics.step_state(state, c);
+            state_ext = extended.step_state(state_ext, complex_from_f64(c));
+            let (z, z_ext) = (dynamics.state_z(&state), complex_to_f64(extended.state_z(&state_ext)));
+            assert!((z_ext - z).norm() <= 1e-9 * z.norm().max(1.0), "{} != {}", z_ext, z);
+        }
+    }
+
+    fn assert_generic<T: Real>() {
+        let p = |re, im| Complex::new(re, im);
+        let t = |z: Complex<Float>| complex_from_f64::<T>(z);
+        let cubic = Polynomial::from_roots(&[p(1.0, 0.0), p(-0.5, 0.8), p(-0.5, -0.8)]);
+
+        for c in [p(0.3, 0.2), p(-0.4, 0.5), p(0.1, -0.6)] {
+            assert_same_orbit::<T, _, _>(&Magnet1::new(), &Magnet1::new(), c);
+            assert_same_orbit::<T, _, _>(&Magnet2::new(), &Magnet2::new(), c);
+            assert_same_orbit::<T, _, _>(&MultibrotReal::new(2.5), &MultibrotReal::new(2.5), c);
+            assert_same_orbit::<T, _, _>(&MultijuliaReal::new(2.5, p(-0.4, 0.6)), &MultijuliaReal::new(2.5, t(p(-0.4, 0.6))), c);
+            assert_same_orbit::<T, _, _>(&Newton::new(cubic.clone()), &Newton::new(cubic.clone()), c + 1.0);
+            assert_same_orbit::<T, _, _>(
+                &Nova::new(cubic.clone(), 0.8, p(1.0, 0.0)),
+                &Nova::new(cubic.clone(), T::from_f64(0.8), t(p(1.0, 0.0))),
+                c,
+            );
+            assert_same_orbit::<T, _, _>(&ExpJulia::new(p(0.3, 0.0)), &ExpJulia::new(t(p(0.3, 0.0))), c);
+            assert_same_orbit::<T, _, _>(&ExpMandelbrot::new(), &ExpMandelbrot::new(), c);
+            assert_same_orbit::<T, _, _>(&SinJulia::new(p(1.0, 0.1)), &SinJulia::new(t(p(1.0, 0.1))), c);
+            assert_same_orbit::<T, _, _>(&SinMandelbrot::new(), &SinMandelbrot::new(), c);
+            assert_same_orbit::<T, _, _>(&CosJulia::new(p(0.7, 0.2)), &CosJulia::new(t(p(0.7, 0.2))), c);
+            assert_same_orbit::<T, _, _>(&CosMandelbrot::new(), &CosMandelbrot::new(), c);
+        }
+    }
+
+    // PhoenixとHybridはReal型ごとに実装している
+    fn assert_stateful_generic<T: Real>()
+    where
+        Phoenix<T>: StatefulDynamics<T>,
+        Hybrid<T>: StatefulDynamics<T>,
+    {
+        let p = |re, im| Complex::new(re, im);
+        let t = |z: Complex<Float>| complex_from_f64::<T>(z);
+
+        for c in [p(0.3, 0.2), p(-0.4, 0.5), p(0.1, -0.6)] {
+            assert_same_orbit::<T, _, _>(&Phoenix::new(p(0.56, 0.0), p(-0.5, 0.0)), &Phoenix::new(t(p(0.56, 0.0)), t(p(-0.5, 0.0))), c);
+
+            let hybrid: Hybrid = Hybrid::new(Mandelbrot::new(), 2).then(BurningShip::new(), 1);
+            let hybrid_ext: Hybrid<T> = Hybrid::new(Mandelbrot::new(), 2).then(BurningShip::new(), 1);
+            assert_same_orbit::<T, _, _>(&hybrid, &hybrid_ext, c);
+        }
+    }
+
+    #[test]
+    fn phoenix_uses_the_previous_iterate() {
+        // c = 0.5, p = -0.5, z0 = i, z_{-1} = 0を手で計算した値(すべて2進で正確に表せる)
+        let phoenix: Phoenix = Phoenix::new(Complex::new(0.5, 0.0), Complex::new(-0.5, 0.0));
+        let expected = [
+            Complex::new(-0.5, 0.0),  // -1 + 0.5 - 0.5·0
+            Complex::new(0.75, -0.5),  // 0.25 + 0.5 - 0.5·i
+            Complex::new(1.0625, -0.75), // (0.3125 - 0.75i) + 0.5 - 0.5·(-0.5)
+        ];
+
+        let z0 = Complex::new(0.0, 1.0);
+        let mut state = phoenix.init_state(z0);
+        assert_eq!(state, (z0, Complex::zero()));
+        let mut z_prev = z0;
+        for z in expected {
+            state = phoenix.step_state(state, Complex::zero());
+            assert_eq!(state, (z, z_prev));
+            z_prev = z;
+        }
+    }
+
+    #[test]
+    fn hybrid_follows_its_schedule() {
+        let hybrid: Hybrid = Hybrid::new(Mandelbrot::new(), 2).then(BurningShip::new(), 1);
+        let c = Complex::new(-0.3, 0.4);
+        let mandelbrot = |z: Complex<Float>| z * z + c;
+        let burning_ship = |z: Complex<Float>| {
+            let w = Complex::new(z.re.abs(), z.im.abs());
+            w * w + c
+        };
+
+        let mut state = hybrid.init_state(c);
+        let mut z = Complex::zero();
+        for n in 0..9 {
+            z = if n % 3 == 2 { burning_ship(z) } else { mandelbrot(z) };
+            state = hybrid.step_state(state, c);
+            assert!((hybrid.state_z(&state) - z).norm() < 1e-12, "{}: {} != {}", n, hybrid.state_z(&state), z);
+            assert_eq!(state.1, (n + 1) % 3);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn hybrid_rejects_zero_repeat() {
+        let _: Hybrid = Hybrid::new(Mandelbrot::new(), 1).then(BurningShip::new(), 0);
+    }
+
+    #[test]
+    fn presets_are_generic() {
+        assert_generic::<DoubleDouble>();
+        assert_generic::<QuadDouble>();
+        assert_generic::<BigFloat<2>>();
+        assert_stateful_generic::<DoubleDouble>();
+        assert_stateful_generic::<QuadDouble>();
+        assert_stateful_generic::<BigFloat<2>>();
+    }
+}
@@ -1,12 +1,19 @@
 use num_complex::{self, Complex};
-use crate::core::complex_dynamics::ComplexDynamics;
+use crate::core::complex_dynamics::StatefulDynamics;
+use crate::util::real::Real;
 use crate::util::types::Float;
 
-pub trait EscapeEvaluator<D: ComplexDynamics> {
+pub trait EscapeEvaluator<D: StatefulDynamics<T>, T: Real = Float> {
     type Output: Copy;
     fn evaluate(
         &self,
         dynamics: &D,
-        c: Complex<Float>,
+        c: Complex<T>,
     ) -> Self::Output;
+
+    // 隣り合うpixelの値a, bが，色の差とは別に違う領域(脱出したか，反復回数など)にあればtrue
+    // Adaptive supersamplingで描き直すpixelを選ぶのに使う．既定では色の差だけで選ぶ
+    fn differs(&self, _a: &Self::Output, _b: &Self::Output) -> bool {
+        false
+    }
 }
@@ -1,32 +1,771 @@
 use num_complex::{self, Complex};
 use crate::prelude::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EscapeByCount {
     max_iter: usize,
     escape_radius: Float,
+    period_tolerance: Float,
+    bailout: Option<Bailout>,  // Noneならdynamicsのbailout()を使う
 }
 
 impl EscapeByCount {
     pub fn new(max_iter: usize, escape_radius: Float) -> Self {
-        Self { max_iter, escape_radius }
+        Self { max_iter, escape_radius, period_tolerance: 0.0, bailout: None }
+    }
+
+    // dynamicsが宣言するbailoutの代わりに使う脱出判定を指定する
+    pub fn with_bailout(mut self, bailout: Bailout) -> Self {
+        self.bailout = Some(bailout);
+        self
+    }
+
+    // 周期検出の許容誤差(PeriodDetectorを参照)．既定の0.0では周期検出を行わない
+    pub fn with_period_tolerance(mut self, tolerance: Float) -> Self {
+        self.period_tolerance = tolerance;
+        self
     }
 }
 
-impl<D: ComplexDynamics> EscapeEvaluator<D> for EscapeByCount {
+impl<T: Real, D: StatefulDynamics<T>> EscapeEvaluator<D, T> for EscapeByCount {
     type Output = usize;
 
-    fn evaluate(&self, dynamics: &D, c: Complex<Float>) -> usize {
-        let escape_radius_sqr = self.escape_radius * self.escape_radius;
-        let mut z = dynamics.initial_z(c);
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> usize {
+        let bailout = self.bailout.as_ref().unwrap_or_else(|| dynamics.state_bailout());
+        if skips_interior(bailout, self.escape_radius) && dynamics.state_is_interior(c) {
+            return self.max_iter;
+        }
+
+        let mut state = dynamics.init_state(c);
+        let mut detector = PeriodDetector::new(state, self.period_tolerance);
 
         for i in 1..=self.max_iter {
-            z = dynamics.step(z, c);
-            if z.norm_sqr() > escape_radius_sqr {
+            state = dynamics.step_state(state, c);
+            if bailout.escaped(complex_to_f64(dynamics.state_z(&state)), self.escape_radius) {
                 return i;
             }
+            // 周期軌道に入った点は脱出しない
+            if detector.check(dynamics, i, state).is_some() {
+                return self.max_iter;
+            }
         }
 
         self.max_iter
     }
-}
\ No newline at end of file
+
+    fn differs(&self, a: &usize, b: &usize) -> bool {
+        a != b
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct EscapeBySmoothCount {
+    max_iter: usize,
+    escape_radius: Float,
+    period_tolerance: Float,
+}
+
+impl EscapeBySmoothCount {
+    pub fn new(max_iter: usize, escape_radius: Float) -> Self {
+        Self { max_iter, escape_radius, period_tolerance: 0.0 }
+    }
+
+    // 周期検出の許容誤差(PeriodDetectorを参照)．既定の0.0では周期検出を行わない
+    pub fn with_period_tolerance(mut self, tolerance: Float) -> Self {
+        self.period_tolerance = tolerance;
+        self
+    }
+}
+
+impl<T: Real, D: StatefulDynamics<T>> EscapeEvaluator<D, T> for EscapeBySmoothCount {
+    type Output = Float;
+
+    // 正規化反復回数(normalized iteration count)を返す
+    // |z| = Rで脱出したときi, |z| = R^dで脱出したときi - 1となるよう連続的に補間する
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> Float {
+        let bailout = dynamics.state_bailout();
+        if skips_interior(bailout, self.escape_radius) && dynamics.state_is_interior(c) {
+            return self.max_iter as Float;
+        }
+
+        let degree = dynamics.state_degree();
+        let mut state = dynamics.init_state(c);
+        let mut detector = PeriodDetector::new(state, self.period_tolerance);
+
+        for i in 1..=self.max_iter {
+            state = dynamics.step_state(state, c);
+            let z = complex_to_f64(dynamics.state_z(&state));
+            if bailout.escaped(z, self.escape_radius) {
+                let nu = smooth_offset(z, self.escape_radius, degree);
+                return (i as Float - nu).clamp(0.0, self.max_iter as Float);
+            }
+            if detector.check(dynamics, i, state).is_some() {
+                break;
+            }
+        }
+
+        self.max_iter as Float
+    }
+
+    // 正規化反復回数は連続なので，脱出したかどうかだけを比べる
+    fn differs(&self, a: &Float, b: &Float) -> bool {
+        let max_iter = self.max_iter as Float;
+        (*a < max_iter) != (*b < max_iter)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct EscapeByDistance {
+    max_iter: usize,
+    escape_radius: Float,
+}
+
+impl EscapeByDistance {
+    pub fn new(max_iter: usize, escape_radius: Float) -> Self {
+        Self { max_iter, escape_radius }
+    }
+}
+
+impl<T: Real, D: DifferentiableDynamics<T>> EscapeEvaluator<D, T> for EscapeByDistance {
+    type Output = Float;
+
+    // 外部距離推定 |z| ln|z| / (2 |dz|) を返す．脱出しない点は0
+    // escape_radiusは大きいほど(例えば1e3以上)推定が正確になる
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> Float {
+        let bailout = dynamics.bailout();
+        let mut z = dynamics.initial_z(c);
+        let mut dz = dynamics.initial_dz(c);
+
+        for _ in 1..=self.max_iter {
+            dz = dynamics.step_dz(z, dz, c);
+            z = dynamics.step(z, c);
+            let z64 = complex_to_f64(z);
+            if bailout.escaped(z64, self.escape_radius) {
+                let norm = z64.norm();
+                let dz_norm = complex_to_f64(dz).norm();
+                if dz_norm == 0.0 {
+                    return Float::INFINITY;
+                }
+                return 0.5 * norm * norm.ln() / dz_norm;
+            }
+        }
+
+        0.0
+    }
+
+    // 距離推定は連続なので，脱出したかどうか(0か)だけを比べる
+    fn differs(&self, a: &Float, b: &Float) -> bool {
+        (*a == 0.0) != (*b == 0.0)
+    }
+}
+
+
+// 1点の反復で得られる情報をまとめたもの．複数のColoringで使い回せる
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscapeResult {
+    pub iter: usize,  // 脱出した反復回数．脱出しなければmax_iter
+    pub z: Complex<Float>,  // 最後のz
+    pub escaped: bool,  // 脱出したか
+    pub min_norm: Float,  // 軌道上の|z|の最小値
+    pub period: Option<usize>,  // 検出された周期
+}
+
+impl EscapeResult {
+    // EscapeBySmoothCountと同じ正規化反復回数．脱出していなければiterをそのまま返す
+    pub fn smooth_iter(&self, escape_radius: Float, degree: Float) -> Float {
+        if !self.escaped {
+            return self.iter as Float;
+        }
+        (self.iter as Float - smooth_offset(self.z, escape_radius, degree)).max(0.0)
+    }
+}
+
+// is_interiorは|z| <= 2に留まる点を返すので，それより緩い円の脱出判定でだけ反復を省略できる
+// 半径が2未満の円や円以外のbailoutでは内部の点でも脱出しうる
+fn skips_interior(bailout: &Bailout, escape_radius: Float) -> bool {
+    matches!(bailout, Bailout::Circle) && escape_radius >= 2.0
+}
+
+// 脱出時のzから，正規化反復回数で整数の反復回数から引く量を求める
+// degree <= 1やescape_radius <= 1では対数が0以下になり補間できないので0(整数の反復回数)にする
+fn smooth_offset(z: Complex<Float>, escape_radius: Float, degree: Float) -> Float {
+    if degree <= 1.0 || escape_radius <= 1.0 {
+        return 0.0;
+    }
+    let log_z = z.norm_sqr().ln() / 2.0;
+    let nu = (log_z / escape_radius.ln()).ln() / degree.ln();
+    if nu.is_finite() { nu } else { 0.0 }
+}
+
+
+// Brent法による周期検出．2の冪の反復ごとにstateを保存し，保存したstateとの距離がtolerance未満なら周期軌道とみなす
+// toleranceは絶対誤差なので，脱出境界の近くでは脱出する点を周期軌道と誤判定しうる
+#[derive(Debug, Clone, Copy)]
+struct PeriodDetector<S: Copy> {
+    saved: S,
+    saved_iter: usize,
+    next_save: usize,
+    tolerance_sqr: Float,
+}
+
+impl<S: Copy> PeriodDetector<S> {
+    fn new(state: S, tolerance: Float) -> Self {
+        Self {
+            saved: state,
+            saved_iter: 0,
+            next_save: 1,
+            tolerance_sqr: tolerance * tolerance,
+        }
+    }
+
+    // i回目の反復後のstateを渡す．周期が検出されればSome(周期)
+    fn check<T, D>(&mut self, dynamics: &D, i: usize, state: S) -> Option<usize>
+    where
+        T: Real,
+        D: StatefulDynamics<T, State = S>,
+    {
+        if self.tolerance_sqr == 0.0 {
+            return None;
+        }
+        if dynamics.state_distance_sqr(&state, &self.saved) < self.tolerance_sqr {
+            return Some(i - self.saved_iter);
+        }
+        if i == self.next_save {
+            self.saved = state;
+            self.saved_iter = i;
+            self.next_save *= 2;
+        }
+        None
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct EscapeByOrbit {
+    max_iter: usize,
+    escape_radius: Float,
+    period_tolerance: Float,
+}
+
+impl EscapeByOrbit {
+    pub fn new(max_iter: usize, escape_radius: Float) -> Self {
+        Self { max_iter, escape_radius, period_tolerance: 0.0 }
+    }
+
+    // 周期検出の許容誤差(PeriodDetectorを参照)．既定の0.0では周期検出を行わない
+    pub fn with_period_tolerance(mut self, tolerance: Float) -> Self {
+        self.period_tolerance = tolerance;
+        self
+    }
+}
+
+impl<T: Real, D: StatefulDynamics<T>> EscapeEvaluator<D, T> for EscapeByOrbit {
+    type Output = EscapeResult;
+
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> EscapeResult {
+        let bailout = dynamics.state_bailout();
+        let mut state = dynamics.init_state(c);
+        let mut z = complex_to_f64(dynamics.state_z(&state));
+        let mut min_norm_sqr = Float::INFINITY;
+        let mut detector = PeriodDetector::new(state, self.period_tolerance);
+
+        for i in 1..=self.max_iter {
+            state = dynamics.step_state(state, c);
+            z = complex_to_f64(dynamics.state_z(&state));
+            let norm_sqr = z.norm_sqr();
+            min_norm_sqr = min_norm_sqr.min(norm_sqr);
+
+            if bailout.escaped(z, self.escape_radius) {
+                return EscapeResult {
+                    iter: i,
+                    z,
+                    escaped: true,
+                    min_norm: min_norm_sqr.sqrt(),
+                    period: None,
+                };
+            }
+
+            if let Some(period) = detector.check(dynamics, i, state) {
+                return EscapeResult {
+                    iter: self.max_iter,
+                    z,
+                    escaped: false,
+                    min_norm: min_norm_sqr.sqrt(),
+                    period: Some(period),
+                };
+            }
+        }
+
+        EscapeResult {
+            iter: self.max_iter,
+            z,
+            escaped: false,
+            min_norm: min_norm_sqr.sqrt(),
+            period: None,
+        }
+    }
+
+    fn differs(&self, a: &EscapeResult, b: &EscapeResult) -> bool {
+        a.escaped != b.escaped || a.iter != b.iter
+    }
+}
+
+
+// orbit trapの形状
+#[derive(Debug, Clone, Copy)]
+pub enum OrbitTrap {
+    Point(Complex<Float>),  // 点
+    Line { point: Complex<Float>, angle: Float },  // pointを通り，実軸からangle[rad]傾いた直線
+    Cross(Complex<Float>),  // 中心を通る実軸・虚軸に平行な2直線
+    Circle { center: Complex<Float>, radius: Float },  // 円周
+}
+
+impl OrbitTrap {
+    // zからtrapまでの距離
+    pub fn distance(&self, z: Complex<Float>) -> Float {
+        match *self {
+            OrbitTrap::Point(p) => (z - p).norm(),
+            OrbitTrap::Line { point, angle } => {
+                let d = z - point;
+                (d.im * angle.cos() - d.re * angle.sin()).abs()
+            }
+            OrbitTrap::Cross(center) => {
+                let d = z - center;
+                d.re.abs().min(d.im.abs())
+            }
+            OrbitTrap::Circle { center, radius } => ((z - center).norm() - radius).abs(),
+        }
+    }
+}
+
+// 軌道がtrapに最も近づいたときの距離と，その反復回数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrapResult {
+    pub distance: Float,
+    pub iter: usize,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct EscapeByOrbitTrap {
+    max_iter: usize,
+    escape_radius: Float,
+    trap: OrbitTrap,
+}
+
+impl EscapeByOrbitTrap {
+    pub fn new(max_iter: usize, escape_radius: Float, trap: OrbitTrap) -> Self {
+        Self { max_iter, escape_radius, trap }
+    }
+}
+
+impl<T: Real, D: StatefulDynamics<T>> EscapeEvaluator<D, T> for EscapeByOrbitTrap {
+    type Output = TrapResult;
+
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> TrapResult {
+        let bailout = dynamics.state_bailout();
+        let mut state = dynamics.init_state(c);
+        let mut nearest = TrapResult { distance: Float::INFINITY, iter: 0 };
+
+        for i in 1..=self.max_iter {
+            state = dynamics.step_state(state, c);
+            let z = complex_to_f64(dynamics.state_z(&state));
+            if bailout.escaped(z, self.escape_radius) {
+                break;
+            }
+
+            let distance = self.trap.distance(z);
+            if distance < nearest.distance {
+                nearest = TrapResult { distance, iter: i };
+            }
+        }
+
+        nearest
+    }
+
+    // trapに最も近づく反復が変わるところで色が不連続になる
+    fn differs(&self, a: &TrapResult, b: &TrapResult) -> bool {
+        a.iter != b.iter
+    }
+}
+
+
+// 収束判定の結果．rootはdynamicsのattractors()のうち収束先のindex
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Convergence {
+    pub root: Option<usize>,
+    pub iter: usize,  // 収束した反復回数．収束しなければmax_iter
+    pub converged: bool,
+}
+
+
+// |z_{n+1} - z_n| < epsilonで収束とみなす．Newton法などのbasin描画用
+#[derive(Debug, Clone)]
+pub struct ConvergeByCount {
+    max_iter: usize,
+    epsilon: Float,
+}
+
+impl ConvergeByCount {
+    pub fn new(max_iter: usize, epsilon: Float) -> Self {
+        Self { max_iter, epsilon }
+    }
+}
+
+// zに最も近いattractorがtolerance以内にあればそのindex
+fn nearest_attractor(attractors: &[Complex<Float>], z: Complex<Float>, tolerance: Float) -> Option<usize> {
+    attractors
+        .iter()
+        .map(|&a| (a - z).norm_sqr())
+        .enumerate()
+        .filter(|&(_, d)| d < tolerance * tolerance)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+impl<T: Real, D: ConvergentDynamics<T>> EscapeEvaluator<D, T> for ConvergeByCount {
+    type Output = Convergence;
+
+    // 差はTのまま求め，attractorsとの比較だけf64で行う
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> Convergence {
+        let epsilon_sqr = self.epsilon * self.epsilon;
+        // 収束判定はepsilon程度の精度なので，根の同定にはそれより緩い誤差を使う
+        let root_tolerance = self.epsilon.sqrt();
+        let mut state = dynamics.init_state(c);
+        let mut z = dynamics.state_z(&state);
+
+        for i in 1..=self.max_iter {
+            state = dynamics.step_state(state, c);
+            let z_next = dynamics.state_z(&state);
+            if (z_next - z).norm_sqr().to_f64() < epsilon_sqr {
+                return Convergence {
+                    root: nearest_attractor(dynamics.attractors(), complex_to_f64(z_next), root_tolerance),
+                    iter: i,
+                    converged: true,
+                };
+            }
+            z = z_next;
+        }
+
+        Convergence { root: None, iter: self.max_iter, converged: false }
+    }
+
+    fn differs(&self, a: &Convergence, b: &Convergence) -> bool {
+        a.converged != b.converged || a.root != b.root
+    }
+}
+
+
+// 脱出と収束の両方を終了条件とする反復の結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    Escaped(usize),  // 脱出した反復回数
+    Converged { root: Option<usize>, iter: usize },  // rootはattractors()のindex
+    Bounded,  // max_iterまでどちらも起きなかった
+}
+
+
+// |z| > escape_radiusで脱出，attractorまでの距離か|z_{n+1} - z_n|がepsilon未満で収束とみなす
+// Magnetのように無限遠と不動点の両方に吸引されるdynamics用
+#[derive(Debug, Clone)]
+pub struct EscapeOrConverge {
+    max_iter: usize,
+    escape_radius: Float,
+    epsilon: Float,
+}
+
+impl EscapeOrConverge {
+    pub fn new(max_iter: usize, escape_radius: Float, epsilon: Float) -> Self {
+        Self { max_iter, escape_radius, epsilon }
+    }
+}
+
+impl<T: Real, D: ConvergentDynamics<T>> EscapeEvaluator<D, T> for EscapeOrConverge {
+    type Output = Termination;
+
+    fn evaluate(&self, dynamics: &D, c: Complex<T>) -> Termination {
+        let bailout = dynamics.state_bailout();
+        let epsilon_sqr = self.epsilon * self.epsilon;
+        let mut state = dynamics.init_state(c);
+        let mut z = dynamics.state_z(&state);
+
+        for i in 1..=self.max_iter {
+            state = dynamics.step_state(state, c);
+            let z_next = dynamics.state_z(&state);
+            let z64 = complex_to_f64(z_next);
+
+            if bailout.escaped(z64, self.escape_radius) {
+                return Termination::Escaped(i);
+            }
+            if let Some(root) = nearest_attractor(dynamics.attractors(), z64, self.epsilon) {
+                return Termination::Converged { root: Some(root), iter: i };
+            }
+            if (z_next - z).norm_sqr().to_f64() < epsilon_sqr {
+                return Termination::Converged { root: None, iter: i };
+            }
+            z = z_next;
+        }
+
+        Termination::Bounded
+    }
+
+    fn differs(&self, a: &Termination, b: &Termination) -> bool {
+        match (a, b) {
+            (Termination::Escaped(_), Termination::Escaped(_)) => false,
+            (Termination::Converged { root: a, .. }, Termination::Converged { root: b, .. }) => a != b,
+            (Termination::Bounded, Termination::Bounded) => false,
+            _ => true,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 周期検出は既定で無効なので，単純な反復と同じ結果になる
+    #[test]
+    fn count_without_period_detection_matches_plain_iteration() {
+        let evaluator = EscapeByCount::new(500, 2.0);
+        for y in 0..40 {
+            for x in 0..60 {
+                let c = Complex::new(-2.0 + 2.5 * x as Float / 60.0, -1.2 + 2.4 * y as Float / 40.0);
+                let mut z = Complex::new(0.0, 0.0);
+                let mut expected = 500;
+                for i in 1..=500 {
+                    z = z * z + c;
+                    if z.norm_sqr() > 4.0 {
+                        expected = i;
+                        break;
+                    }
+                }
+                let count: usize = evaluator.evaluate(&Mandelbrot::new(), c);
+                assert_eq!(count, expected, "c = {}", c);
+            }
+        }
+    }
+
+    // 内部判定による省略は，脱出判定が半径2以上の円のときだけ行う
+    #[test]
+    fn interior_shortcut_keeps_small_radius_and_custom_bailouts() {
+        let c = Complex::new(-0.7, 0.0);
+        let count: usize = EscapeByCount::new(100, 0.5).evaluate(&Mandelbrot::new(), c);
+        assert_eq!(count, 1);
+        let value: Float = EscapeBySmoothCount::new(100, 0.5).evaluate(&Mandelbrot::new(), c);
+        assert_eq!(value, 1.0);
+
+        // 主カージオイド内のc = 0.2では|Re z|は0.2を超える
+        let evaluator = EscapeByCount::new(100, 0.25).with_bailout(Bailout::RealPart);
+        let count: usize = evaluator.evaluate(&Mandelbrot::new(), Complex::new(0.2, 0.0));
+        assert!(count < 100, "count = {}", count);
+
+        let count: usize = EscapeByCount::new(100, 2.0).evaluate(&Mandelbrot::new(), c);
+        assert_eq!(count, 100);
+    }
+
+    #[test]
+    fn period_detection_stops_interior_orbits() {
+        // c = -1は周期2の吸引軌道を持つ
+        let result = EscapeByOrbit::new(1000, 2.0)
+            .with_period_tolerance(1e-12)
+            .evaluate(&Mandelbrot::new(), Complex::new(-1.0, 0.0));
+        assert!(!result.escaped);
+        assert_eq!(result.period, Some(2));
+
+        let result = EscapeByOrbit::new(1000, 2.0).evaluate(&Mandelbrot::new(), Complex::new(-1.0, 0.0));
+        assert_eq!(result.period, None);
+    }
+
+    #[test]
+    fn custom_bailout_is_borrowed_not_cloned() {
+        let bailout = Bailout::custom(|z, r| z.re.abs() > r);
+        let evaluator = EscapeByCount::new(100, 2.0).with_bailout(bailout);
+        let count: usize = evaluator.evaluate(&Mandelbrot::new(), Complex::new(1.0, 0.0));
+        assert_eq!(count, 3);
+
+        let Some(Bailout::Custom(f)) = &evaluator.bailout else { unreachable!() };
+        for x in 0..100 {
+            let _: usize = evaluator.evaluate(&Mandelbrot::new(), Complex::new(-2.0 + 0.03 * x as Float, 0.5));
+        }
+        assert_eq!(std::sync::Arc::strong_count(f), 1);
+    }
+
+    #[test]
+    fn smooth_count_is_finite_for_degenerate_degree_and_radius() {
+        // z -> z + cはdegree 1なので補間できず，整数の反復回数になる
+        let linear = MultibrotReal::new(1.0);
+        let value = EscapeBySmoothCount::new(100, 2.0).evaluate(&linear, Complex::new(1.0, 0.0));
+        assert_eq!(value, 3.0);
+
+        for radius in [1.0, 0.5] {
+            let evaluator = EscapeBySmoothCount::new(100, radius);
+            for re in [-2.5, -1.9, 0.3, 0.5, 1.0] {
+                let value: Float = evaluator.evaluate(&Mandelbrot::new(), Complex::new(re, 0.1));
+                assert!(value.is_finite(), "radius {} re {} gave {}", radius, re, value);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_iter_is_finite_for_degenerate_degree() {
+        let result = EscapeByOrbit::new(100, 2.0).evaluate(&Mandelbrot::new(), Complex::new(1.0, 0.0));
+        assert!(result.escaped);
+        assert_eq!(result.smooth_iter(2.0, 1.0), result.iter as Float);
+        assert!(result.smooth_iter(2.0, 2.0).is_finite());
+    }
+
+    // step_dzで追跡したdzが，n回反復後のzをcで中心差分した値と一致する
+    // Julia型ではcが初期値z0なので，どちらもcについての微分になる
+    fn assert_dz_matches_finite_difference<D: DifferentiableDynamics>(dynamics: &D, c: Complex<Float>) {
+        let orbit = |c: Complex<Float>| {
+            let mut z = dynamics.initial_z(c);
+            for _ in 0..6 {
+                z = dynamics.step(z, c);
+            }
+            z
+        };
+        let mut z = dynamics.initial_z(c);
+        let mut dz = dynamics.initial_dz(c);
+        for _ in 0..6 {
+            dz = dynamics.step_dz(z, dz, c);
+            z = dynamics.step(z, c);
+        }
+
+        let h = 1e-6;
+        for dir in [Complex::new(h, 0.0), Complex::new(0.0, h)] {
+            let expected = (orbit(c + dir) - orbit(c - dir)) / (2.0 * dir);
+            assert!((dz - expected).norm() < 1e-6 * expected.norm().max(1.0), "c = {}: {} != {}", c, dz, expected);
+        }
+    }
+
+    #[test]
+    fn dz_matches_finite_difference() {
+        let julia_c = Complex::new(-0.4, 0.6);
+        for c in [Complex::new(-0.5, 0.5), Complex::new(0.1, -0.3), Complex::new(-1.2, 0.1)] {
+            assert_dz_matches_finite_difference(&Mandelbrot::new(), c);
+            assert_dz_matches_finite_difference(&Julia::new(julia_c), c);
+            assert_dz_matches_finite_difference(&Multibrot::new(3), c);
+            assert_dz_matches_finite_difference(&Multijulia::new(3, julia_c), c);
+            assert_dz_matches_finite_difference(&MultibrotReal::new(2.5), c);
+            assert_dz_matches_finite_difference(&MultijuliaReal::new(2.5, julia_c), c);
+        }
+    }
+
+    #[test]
+    fn distance_estimate_is_within_koebe_bounds() {
+        // c = 0のJulia集合は単位円板なので，z0から集合までの距離は|z0| - 1
+        let julia = Julia::new(Complex::new(0.0, 0.0));
+        let evaluator = EscapeByDistance::new(100, 1e3);
+        for r in [1.01, 1.5, 2.0, 3.0] {
+            for angle in [0.0, 1.0, 2.5, -2.0] {
+                let z0 = Complex::from_polar(r, angle);
+                let estimate: Float = evaluator.evaluate(&julia, z0);
+                let exact = r - 1.0;
+                assert!(estimate >= 0.25 * exact && estimate <= exact, "z0 = {}: {} vs {}", z0, estimate, exact);
+            }
+        }
+
+        // 脱出しない点は0
+        let bounded: Float = evaluator.evaluate(&julia, Complex::new(0.5, 0.5));
+        assert_eq!(bounded, 0.0);
+        let bounded: Float = evaluator.evaluate(&Mandelbrot::new(), Complex::new(-1.0, 0.0));
+        assert_eq!(bounded, 0.0);
+    }
+
+    #[test]
+    fn trap_distances() {
+        let p = Complex::new;
+        assert_eq!(OrbitTrap::Point(p(1.0, 1.0)).distance(p(4.0, 5.0)), 5.0);
+        assert_eq!(OrbitTrap::Line { point: p(0.0, 1.0), angle: 0.0 }.distance(p(3.0, -2.0)), 3.0);
+        let diagonal = OrbitTrap::Line { point: p(1.0, 0.0), angle: std::f64::consts::FRAC_PI_4 };
+        assert!((diagonal.distance(p(3.0, 0.0)) - Float::sqrt(2.0)).abs() < 1e-15);
+        assert!(diagonal.distance(p(-2.0, -3.0)) < 1e-15);
+        let vertical = OrbitTrap::Line { point: p(1.0, 0.0), angle: std::f64::consts::FRAC_PI_2 };
+        assert!((vertical.distance(p(4.0, 7.0)) - 3.0).abs() < 1e-15);
+        assert_eq!(OrbitTrap::Cross(p(1.0, 1.0)).distance(p(4.0, 1.5)), 0.5);
+        assert_eq!(OrbitTrap::Cross(p(1.0, 1.0)).distance(p(-1.0, 5.0)), 2.0);
+        let circle = OrbitTrap::Circle { center: p(0.0, 0.0), radius: 2.0 };
+        assert_eq!(circle.distance(p(0.5, 0.0)), 1.5);
+        assert_eq!(circle.distance(p(3.0, 4.0)), 3.0);
+    }
+
+    #[test]
+    fn trap_result_keeps_the_closest_iteration() {
+        // c = 0.3の軌道0.3, 0.39, 0.4521, …は0.4に2回目で最も近づき，その後ゆっくり脱出する
+        let evaluator = EscapeByOrbitTrap::new(100, 2.0, OrbitTrap::Point(Complex::new(0.4, 0.0)));
+        let c = Complex::new(0.3, 0.0);
+        let result = evaluator.evaluate(&Mandelbrot::new(), c);
+        assert_eq!(result.iter, 2);
+        assert!((result.distance - 0.01).abs() < 1e-15, "{}", result.distance);
+        let escape_iter: usize = EscapeByCount::new(100, 2.0).evaluate(&Mandelbrot::new(), c);
+        assert!(escape_iter > 5 && escape_iter < 100);
+
+        // 脱出しない軌道でも最初に最も近づいた反復を返す(c = -1では0, -1, 0, …)
+        let evaluator = EscapeByOrbitTrap::new(100, 2.0, OrbitTrap::Point(Complex::new(0.0, 0.0)));
+        let result = evaluator.evaluate(&Mandelbrot::new(), Complex::new(-1.0, 0.0));
+        assert_eq!(result, TrapResult { distance: 0.0, iter: 2 });
+    }
+
+    #[test]
+    fn newton_converges_to_the_nearest_cube_root_of_unity() {
+        let newton = Newton::new(Polynomial::new(vec![Complex::new(-1.0, 0.0), Complex::ZERO, Complex::ZERO, Complex::ONE]));
+        let roots = <Newton as ConvergentDynamics>::attractors(&newton).to_vec();
+        let evaluator = ConvergeByCount::new(50, 1e-10);
+        for (i, &root) in roots.iter().enumerate() {
+            for offset in [Complex::new(0.1, 0.0), Complex::new(0.0, -0.15), Complex::new(-0.05, 0.05)] {
+                let result = evaluator.evaluate(&newton, root + offset);
+                assert!(result.converged);
+                assert_eq!(result.root, Some(i), "start {}", root + offset);
+                assert!(result.iter < 50);
+            }
+        }
+
+        // z = 0ではp'(z) = 0で反復が定義できず，収束しない
+        let result = evaluator.evaluate(&newton, Complex::new(0.0, 0.0));
+        assert_eq!(result, Convergence { root: None, iter: 50, converged: false });
+    }
+
+    #[test]
+    fn magnet_escapes_or_converges_to_one() {
+        let evaluator = EscapeOrConverge::new(200, 100.0, 1e-6);
+        // z = 1はcによらず不動点で，c = 4や-2では吸引的
+        for c in [4.0, -2.0, 10.0] {
+            let result = evaluator.evaluate(&Magnet1::new(), Complex::new(c, 0.0));
+            assert!(matches!(result, Termination::Converged { root: Some(0), .. }), "c = {}: {:?}", c, result);
+        }
+        // 分母2z + c - 2が0に近いc = 2.5では数回で脱出する
+        let result = evaluator.evaluate(&Magnet1::new(), Complex::new(2.5, 0.0));
+        assert_eq!(result, Termination::Escaped(4));
+        // c = 0.5ではz = 1ではない不動点に収束するので，rootはNone
+        let result = evaluator.evaluate(&Magnet1::new(), Complex::new(0.5, 0.0));
+        assert!(matches!(result, Termination::Converged { root: None, .. }), "{:?}", result);
+    }
+
+    #[test]
+    fn nova_applies_the_relaxation_factor() {
+        let cubic = Polynomial::new(vec![Complex::new(-1.0, 0.0), Complex::ZERO, Complex::ZERO, Complex::ONE]);
+        let c: Complex<Float> = Complex::new(0.1, 0.05);
+        for relaxation in [1.0 as Float, 0.5, 0.8] {
+            let nova = Nova::new(cubic.clone(), relaxation, Complex::new(1.0, 0.0));
+            let mut z = nova.initial_z(c);
+            let mut expected = Complex::new(1.0, 0.0);
+            for _ in 0..5 {
+                z = nova.step(z, c);
+                expected = expected - relaxation * (expected.powi(3) - 1.0) / (3.0 * expected * expected) + c;
+                assert!((z - expected).norm() < 1e-12, "{} != {}", z, expected);
+            }
+
+            // 不動点ではrelaxation * p(z) / p'(z) = cになる．既知のattractorはないのでrootはNone
+            let result = ConvergeByCount::new(200, 1e-12).evaluate(&nova, c);
+            assert!(result.converged && result.root.is_none(), "{:?}", result);
+            let mut z = nova.initial_z(c);
+            for _ in 0..result.iter {
+                z = nova.step(z, c);
+            }
+            assert!((relaxation * (z.powi(3) - 1.0) / (3.0 * z * z) - c).norm() < 1e-9);
+        }
+    }
+}
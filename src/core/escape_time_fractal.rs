@@ -1,31 +1,76 @@
 use crate::util::color::Color;
-use crate::core::complex_dynamics::ComplexDynamics;
+use crate::core::complex_dynamics::StatefulDynamics;
 use crate::core::escape_evaluator::EscapeEvaluator;
 use crate::core::coloring::Coloring;
+use crate::core::supersampling::Supersampling;
+use crate::util::real::{Real, complex_from_f64};
 use crate::util::types::Float;
 
+use std::sync::OnceLock;
+
 use rayon::prelude::*;
 use num_complex::{self, Complex};
-use image::{Rgb, RgbImage};
+use image::RgbImage;
+
+// Mariani-Silver法でこれ以下の面積の長方形は分けずに計算する
+const MIN_SUBDIVIDE_AREA: usize = 16;
+
+// Mariani-Silver法の設定
+// 境界に触れない島(集合の内部に囲まれた脱出する領域など)は，辺がmax_fill_side以下なら見落としうる
+// その大きさより小さい構造が要る描画では使わないか，max_fill_sideを小さくする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subdivision {
+    pub max_fill_side: usize,  // 境界が同じ値でも，辺がこれより長い長方形は塗らずに分ける
+}
+
+impl Subdivision {
+    pub fn new(max_fill_side: usize) -> Self {
+        Self { max_fill_side }
+    }
+}
+
+impl Default for Subdivision {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+// Mariani-Silver法で1pixel 1点の値を求める関数．Fは描画するfractal，Oはその値
+type SubdivideFn<F, O> = fn(&F, Subdivision, bool) -> Vec<O>;
 
-pub struct EscapeTimeFractal<D, E, C>
+// 両端を含む長方形(x0, y0, x1, y1)の境界のpixel
+fn border(rect: (usize, usize, usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    let (x0, y0, x1, y1) = rect;
+    let horizontal = (x0..=x1).flat_map(move |x| [(x, y0), (x, y1)]);
+    let vertical = (y0 + 1..y1).flat_map(move |y| [(x0, y), (x1, y)]);
+    horizontal.chain(vertical)
+}
+
+// Tは反復に使う実数型．centerだけTで持ち，centerからのずれはFloatで計算する
+pub struct EscapeTimeFractal<D, E, C, T = Float>
 where
-    D: ComplexDynamics,
-    E: EscapeEvaluator<D>,
+    T: Real,
+    D: StatefulDynamics<T>,
+    E: EscapeEvaluator<D, T>,
     C: Coloring<E::Output>
 {
     pub dynamics: D,  // 力学系の定義
     pub escape: E,  // escape評価器
     pub coloring: C,  // 色付け
     pub resolution: (usize, usize),  // 描画画像サイズ(w, h)
-    pub center: Complex<Float>,  // 描画の中心の複素数座標
+    pub center: Complex<T>,  // 描画の中心の複素数座標
     pub view_size: (Float, Float),  // 描画する範囲(re, im)
+    pub supersampling: Supersampling,  // 1pixel内の標本点の取り方
+    // 1pixel 1点の値をMariani-Silver法で求めるときの設定と，その計算
+    // 境界の比較にE::Output: PartialEqが要るので，with_subdivisionで関数ごと渡す
+    subdivision: Option<(Subdivision, SubdivideFn<Self, E::Output>)>,
 }
 
-impl<D, E, C> EscapeTimeFractal<D, E, C>
+impl<D, E, C, T> EscapeTimeFractal<D, E, C, T>
 where
-    D: ComplexDynamics + Sync,
-    E: EscapeEvaluator<D> + Sync,
+    T: Real,
+    D: StatefulDynamics<T> + Sync,
+    E: EscapeEvaluator<D, T> + Sync,
     C: Coloring<E::Output> + Sync,
     E::Output: Sync + Send,
 {
@@ -34,7 +79,7 @@ where
         escape: E,
         coloring: C,
         resolution: (usize, usize),
-        center: Complex<Float>,
+        center: Complex<T>,
         view_size: (Float, Float),
     ) -> Self {
         EscapeTimeFractal {
@@ -44,37 +89,68 @@ where
             resolution,
             center,
             view_size,
+            supersampling: Supersampling::None,
+            subdivision: None,
         }
     }
 
-    // (remin, remax, immin, immax)を返す
+    pub fn with_supersampling(mut self, supersampling: Supersampling) -> Self {
+        self.supersampling = supersampling;
+        self
+    }
+
+    // colors, renderなどで1pixel 1点の値をMariani-Silver法で求める
+    // Supersampling::NoneとAdaptiveの1回目の描画で使い，標本点を複数取る方法では使わない
+    pub fn with_subdivision(mut self, subdivision: Subdivision) -> Self
+    where
+        E::Output: PartialEq,
+    {
+        self.subdivision = Some((subdivision, Self::subdivide_all));
+        self
+    }
+
+    // centerからの相対座標で(remin, remax, immin, immax)を返す
     #[inline]
     fn view_bounds(&self) -> (Float, Float, Float, Float) {
         let (w, h) = self.view_size;
-        (
-            self.center.re - w / 2.0,
-            self.center.re + w / 2.0,
-            self.center.im - h / 2.0,
-            self.center.im + h / 2.0,
-        )
+        (-w / 2.0, w / 2.0, -h / 2.0, h / 2.0)
     }
 
+    // pointはpixel単位の座標．整数ならそのpixelを代表する点
     fn pixel_to_complex(
         &self,
-        point: (usize, usize),
+        point: (Float, Float),
         view_bounds: (Float, Float, Float, Float),
-    ) -> Complex<Float> {
+    ) -> Complex<T> {
         let (x, y) = point;
         let (re_min, re_max, im_min, im_max) = view_bounds;
         let (w, h) = self.resolution;
 
-        let t = x as Float / w as Float;
+        let t = x / w as Float;
         let re = re_min + t * (re_max - re_min);
 
-        let t = y as Float / h as Float;
+        let t = y / h as Float;
         let im = im_max + t * (im_min - im_max);
 
-        Complex {re, im}
+        self.center + complex_from_f64(Complex {re, im})
+    }
+
+    // supersamplingの標本点ごとに色を付けて平均する
+    // 標本点はescape_valuesと同じ代表点を中心に取るので，Noneではescape_valuesの色と一致する
+    fn pixel_color(
+        &self,
+        point: (usize, usize),
+        view_bounds: (Float, Float, Float, Float),
+    ) -> Color {
+        if self.supersampling == Supersampling::None {
+            return self.coloring.color(self.evaluate_pixel(point, view_bounds));
+        }
+        let (x, y) = (point.0 as Float, point.1 as Float);
+        Color::mean_of((0..self.supersampling.samples()).map(|k| {
+            let (dx, dy) = self.supersampling.offset(point, k);
+            let z = self.pixel_to_complex((x + dx, y + dy), view_bounds);
+            self.coloring.color(self.escape.evaluate(&self.dynamics, z))
+        }))
     }
 
     pub fn escape_values(&self) -> Vec<E::Output> {
@@ -86,7 +162,7 @@ where
             .map(|i| {
                 let x = i % w;
                 let y = i / w;
-                let z = self.pixel_to_complex((x, y), bounds);
+                let z = self.pixel_to_complex((x as Float, y as Float), bounds);
                 self.escape.evaluate(&self.dynamics, z)
             })
             .collect()
@@ -101,12 +177,230 @@ where
             .map(|i| {
                 let x = i % w;
                 let y = i / w;
-                let z = self.pixel_to_complex((x, y), bounds);
+                let z = self.pixel_to_complex((x as Float, y as Float), bounds);
                 self.escape.evaluate(&self.dynamics, z)
             })
             .collect()
     }
 
+    // 1pixel 1点の値．subdivisionが有効ならMariani-Silver法で求める
+    fn sample_values(&self) -> Vec<E::Output> {
+        match self.subdivision {
+            Some((subdivision, subdivide_all)) => subdivide_all(self, subdivision, false),
+            None => self.escape_values(),
+        }
+    }
+
+    fn sample_values_par(&self) -> Vec<E::Output> {
+        match self.subdivision {
+            Some((subdivision, subdivide_all)) => subdivide_all(self, subdivision, true),
+            None => self.escape_values_par(),
+        }
+    }
+
+    // supersamplingした各pixelの色
+    pub fn colors(&self) -> Vec<Color> {
+        match self.supersampling {
+            Supersampling::Adaptive { threshold, .. } => {
+                let values = self.sample_values();
+                return self.refine_colors(&values, &self.colors_from_values(&values), threshold);
+            }
+            Supersampling::None => return self.colors_from_values(&self.sample_values()),
+            _ => {}
+        }
+
+        let (w, h) = self.resolution;
+        let bounds = self.view_bounds();
+
+        (0..w * h)
+            .map(|i| self.pixel_color((i % w, i / w), bounds))
+            .collect()
+    }
+
+    pub fn colors_par(&self) -> Vec<Color> {
+        match self.supersampling {
+            Supersampling::Adaptive { threshold, .. } => {
+                let values = self.sample_values_par();
+                return self.refine_colors_par(&values, &self.colors_from_values_par(&values), threshold);
+            }
+            Supersampling::None => return self.colors_from_values_par(&self.sample_values_par()),
+            _ => {}
+        }
+
+        let (w, h) = self.resolution;
+        let bounds = self.view_bounds();
+
+        (0..w * h)
+            .into_par_iter()
+            .map(|i| self.pixel_color((i % w, i / w), bounds))
+            .collect()
+    }
+
+    // 1pixel 1点で求めた値valuesとその色colorsのうち，上下左右のpixelと色の成分がthresholdより
+    // 大きく違うか，escape.differsで違う領域にあるpixelだけsupersamplingで描き直す
+    // 標本点はvaluesと同じ代表点を中心に取る
+    pub fn refine_colors(&self, values: &[E::Output], colors: &[Color], threshold: u8) -> Vec<Color> {
+        let (w, h) = self.resolution;
+        assert_eq!(values.len(), w * h);
+        assert_eq!(colors.len(), w * h);
+        let bounds = self.view_bounds();
+
+        (0..w * h)
+            .map(|i| self.refined_color(values, colors, i, threshold, bounds))
+            .collect()
+    }
+
+    pub fn refine_colors_par(&self, values: &[E::Output], colors: &[Color], threshold: u8) -> Vec<Color> {
+        let (w, h) = self.resolution;
+        assert_eq!(values.len(), w * h);
+        assert_eq!(colors.len(), w * h);
+        let bounds = self.view_bounds();
+
+        (0..w * h)
+            .into_par_iter()
+            .map(|i| self.refined_color(values, colors, i, threshold, bounds))
+            .collect()
+    }
+
+    fn refined_color(
+        &self,
+        values: &[E::Output],
+        colors: &[Color],
+        i: usize,
+        threshold: u8,
+        view_bounds: (Float, Float, Float, Float),
+    ) -> Color {
+        let (w, h) = self.resolution;
+        let (x, y) = (i % w, i / w);
+
+        let neighbours = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < w).then(|| i + 1),
+            (y > 0).then(|| i - w),
+            (y + 1 < h).then(|| i + w),
+        ];
+        let edge = neighbours
+            .into_iter()
+            .flatten()
+            .any(|j| colors[i].max_diff(&colors[j]) > threshold || self.escape.differs(&values[i], &values[j]));
+
+        if edge { self.pixel_color((x, y), view_bounds) } else { colors[i] }
+    }
+
+    // Mariani-Silver法．長方形の境界を計算し，境界がすべて同じ値なら内部を同じ値で塗り，
+    // そうでなければ2つに分けて繰り返す．集合の内部や外側の広い領域の計算を省ける
+    // 大きい長方形は境界が同じでも分けるので，辺がmax_fill_sideより大きい島は見落とさない
+    // 結果はescape_valuesと同じ並び
+    pub fn escape_values_subdivided(&self, subdivision: Subdivision) -> Vec<E::Output>
+    where
+        E::Output: PartialEq,
+    {
+        self.subdivide_all(subdivision, false)
+    }
+
+    pub fn escape_values_subdivided_par(&self, subdivision: Subdivision) -> Vec<E::Output>
+    where
+        E::Output: PartialEq,
+    {
+        self.subdivide_all(subdivision, true)
+    }
+
+    fn subdivide_all(&self, subdivision: Subdivision, par: bool) -> Vec<E::Output>
+    where
+        E::Output: PartialEq,
+    {
+        let (w, h) = self.resolution;
+        if w == 0 || h == 0 {
+            return Vec::new();
+        }
+        let bounds = self.view_bounds();
+        let values: Vec<OnceLock<E::Output>> = (0..w * h).map(|_| OnceLock::new()).collect();
+
+        // 画像の外周を計算しておく
+        let rect = (0, 0, w - 1, h - 1);
+        for (x, y) in border(rect) {
+            let _ = values[y * w + x].set(self.evaluate_pixel((x, y), bounds));
+        }
+        self.subdivide(&values, rect, bounds, subdivision, par);
+
+        values
+            .into_iter()
+            .map(|v| v.into_inner().expect("every pixel should be filled"))
+            .collect()
+    }
+
+    // rect = (x0, y0, x1, y1)は両端を含む．境界は計算済みで，内部を埋める
+    fn subdivide(
+        &self,
+        values: &[OnceLock<E::Output>],
+        rect: (usize, usize, usize, usize),
+        view_bounds: (Float, Float, Float, Float),
+        subdivision: Subdivision,
+        par: bool,
+    )
+    where
+        E::Output: PartialEq,
+    {
+        let (x0, y0, x1, y1) = rect;
+        let w = self.resolution.0;
+        if x1 - x0 < 2 || y1 - y0 < 2 {
+            return;
+        }
+        let interior = (x0 + 1..x1).flat_map(|x| (y0 + 1..y1).map(move |y| (x, y)));
+        let get = |(x, y): (usize, usize)| values[y * w + x].get().copied();
+
+        let first = get((x0, y0));
+        let small = x1 - x0 <= subdivision.max_fill_side && y1 - y0 <= subdivision.max_fill_side;
+        if small && border(rect).all(|p| get(p) == first) {
+            for (x, y) in interior {
+                let _ = values[y * w + x].set(first.expect("border should be computed"));
+            }
+            return;
+        }
+
+        // 小さい長方形は分けずに全部計算する
+        if (x1 - x0 - 1) * (y1 - y0 - 1) <= MIN_SUBDIVIDE_AREA {
+            for (x, y) in interior {
+                let _ = values[y * w + x].set(self.evaluate_pixel((x, y), view_bounds));
+            }
+            return;
+        }
+
+        // 長い方の辺を半分にし，分け目の線を計算してから両側を埋める
+        let (a, b) = if x1 - x0 >= y1 - y0 {
+            let xm = (x0 + x1) / 2;
+            for y in y0 + 1..y1 {
+                let _ = values[y * w + xm].set(self.evaluate_pixel((xm, y), view_bounds));
+            }
+            ((x0, y0, xm, y1), (xm, y0, x1, y1))
+        } else {
+            let ym = (y0 + y1) / 2;
+            for x in x0 + 1..x1 {
+                let _ = values[ym * w + x].set(self.evaluate_pixel((x, ym), view_bounds));
+            }
+            ((x0, y0, x1, ym), (x0, ym, x1, y1))
+        };
+
+        if par {
+            rayon::join(
+                || self.subdivide(values, a, view_bounds, subdivision, par),
+                || self.subdivide(values, b, view_bounds, subdivision, par),
+            );
+        } else {
+            self.subdivide(values, a, view_bounds, subdivision, par);
+            self.subdivide(values, b, view_bounds, subdivision, par);
+        }
+    }
+
+    fn evaluate_pixel(
+        &self,
+        point: (usize, usize),
+        view_bounds: (Float, Float, Float, Float),
+    ) -> E::Output {
+        let z = self.pixel_to_complex((point.0 as Float, point.1 as Float), view_bounds);
+        self.escape.evaluate(&self.dynamics, z)
+    }
+
     pub fn colors_from_values(&self, values: &[E::Output]) -> Vec<Color> {
         values
             .iter()
@@ -197,24 +491,190 @@ where
     }
 
     pub fn render(&self) -> RgbImage {
-        let (w, h) = self.resolution;
-        let bounds = self.view_bounds();
-        let mut img = RgbImage::new(w as u32, h as u32);
+        let cs = self.colors();
+        self.render_from_colors(&cs)
+    }
+
+    pub fn render_par(&self) -> RgbImage {
+        let cs = self.colors_par();
+        self.render_from_colors_par(&cs)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::coloring_presets::PaletteColoring;
+    use crate::core::complex_dynamics::ComplexDynamics;
+    use crate::core::complex_dynamics_presets::Mandelbrot;
+    use crate::core::escape_evaluator_presets::EscapeByCount;
+    use crate::util::palette::Palette;
+
+    const MAX_ITER: usize = 64;
+
+    fn fractal(supersampling: Supersampling) -> EscapeTimeFractal<Mandelbrot, EscapeByCount, PaletteColoring> {
+        let coloring = PaletteColoring::new(Palette::grayscale(MAX_ITER), MAX_ITER);
+        EscapeTimeFractal::new(
+            Mandelbrot::new(),
+            EscapeByCount::new(MAX_ITER, 2.0),
+            coloring,
+            (24, 16),
+            Complex::new(-0.75, 0.1),
+            (0.6, 0.4),
+        )
+        .with_supersampling(supersampling)
+    }
+
+    #[test]
+    fn single_sample_matches_escape_values() {
+        // NoneとGrid(1)はどちらもescape_valuesと同じ点で1回ずつ評価する
+        let expected = {
+            let f = fractal(Supersampling::None);
+            f.colors_from_values(&f.escape_values())
+        };
+        for mode in [Supersampling::None, Supersampling::Grid(1), Supersampling::RotatedGrid(1)] {
+            let f = fractal(mode);
+            assert_eq!(f.colors(), expected, "{:?}", mode);
+            assert_eq!(f.colors_par(), expected, "{:?}", mode);
+        }
+    }
 
-        for (x, y, pixel) in img.enumerate_pixels_mut() {
-            let c = self.pixel_to_complex((x as usize, y as usize), bounds);
-            let escape_value = self.escape.evaluate(&self.dynamics, c);
-            let color = self.coloring.color(escape_value);
-            *pixel = Rgb([color.get_r(), color.get_g(), color.get_b()]);
+    #[test]
+    fn supersampled_colors_average_the_offsets() {
+        let f = fractal(Supersampling::Grid(2));
+        let bounds = f.view_bounds();
+        let colors = f.colors();
+        assert_eq!(colors, f.colors_par());
+
+        let (w, _) = f.resolution;
+        for (i, &color) in colors.iter().enumerate() {
+            let (x, y) = (i % w, i / w);
+            let samples: Vec<Color> = f.supersampling
+                .offsets((x, y))
+                .into_iter()
+                .map(|(dx, dy)| {
+                    let z = f.pixel_to_complex((x as Float + dx, y as Float + dy), bounds);
+                    f.coloring.color(f.escape.evaluate(&f.dynamics, z))
+                })
+                .collect();
+            assert_eq!(color, Color::mean(&samples));
         }
+    }
 
-        img
+    #[test]
+    fn adaptive_refines_pixels_whose_escape_values_differ() {
+        // 色の差では描き直さないthresholdでも，反復回数が違う隣があるpixelは描き直す
+        let adaptive = fractal(Supersampling::Adaptive { n: 3, threshold: u8::MAX });
+        let values = adaptive.escape_values();
+        let single = adaptive.colors_from_values(&values);
+        let refined = fractal(Supersampling::RotatedGrid(3)).colors();
+        let colors = adaptive.colors();
+        assert_eq!(colors, adaptive.colors_par());
+
+        let (w, h) = adaptive.resolution;
+        let mut refined_count = 0;
+        for (i, &color) in colors.iter().enumerate() {
+            let (x, y) = (i % w, i / w);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < w).then(|| i + 1),
+                (y > 0).then(|| i - w),
+                (y + 1 < h).then(|| i + w),
+            ];
+            let edge = neighbours
+                .into_iter()
+                .flatten()
+                .any(|j| values[i] != values[j]);
+            if edge {
+                assert_eq!(color, refined[i]);
+                refined_count += 1;
+            } else {
+                // 描き直さないpixelは1点目の描画と同じ点の色のまま
+                assert_eq!(color, single[i]);
+            }
+        }
+        assert!(refined_count > 0 && refined_count < w * h, "{}", refined_count);
     }
 
-    pub fn render_par(&self) -> RgbImage {
-        let vs = self.escape_values_par();
-        let cs = self.colors_from_values_par(&vs);
-        self.render_from_colors_par(&cs)
+    // 中心の円板だけで脱出し，それ以外は0に留まる力学系．画像の外周はすべて内部になる
+    struct Island(Float);
+
+    impl ComplexDynamics for Island {
+        fn initial_z(&self, _c: Complex<Float>) -> Complex<Float> {
+            Complex::new(0.0, 0.0)
+        }
+        fn step(&self, _z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+            if c.norm() < self.0 { Complex::new(1e10, 0.0) } else { Complex::new(0.0, 0.0) }
+        }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn subdivision_matches_escape_values() {
+        let subdivision = Subdivision::default();
+        let f = fractal(Supersampling::None).with_subdivision(subdivision);
+        let expected = f.escape_values();
+        assert_eq!(f.escape_values_subdivided(subdivision), expected);
+        assert_eq!(f.escape_values_subdivided_par(subdivision), expected);
+        assert_eq!(f.colors(), f.colors_from_values(&expected));
+        assert_eq!(f.colors_par(), f.colors_from_values(&expected));
+
+        // 集合の内部が広く，塗りつぶしが起きる画像
+        let mut f = f;
+        f.center = Complex::new(-0.2, 0.0);
+        f.view_size = (0.6, 0.4);
+        f.resolution = (64, 48);
+        assert_eq!(f.escape_values_subdivided(subdivision), f.escape_values());
+    }
+
+    // 実際のMandelbrot集合の画像でも1pixelずつ計算したものと一致する
+    // seahorse valleyの細い脱出領域は既定のmax_fill_sideでは見落とすので，小さくして比べる
+    #[test]
+    fn subdivision_matches_per_pixel_on_mandelbrot_views() {
+        let max_iter = 256;
+        let views = [
+            (Complex::new(-0.75, 0.0), (3.0, 2.25), Subdivision::default()),  // 全体
+            (Complex::new(-0.7436, 0.1318), (0.002, 0.0015), Subdivision::default()),
+            (Complex::new(-1.7685, 0.0), (0.004, 0.003), Subdivision::default()),  // 実軸上の小さなMandelbrot集合
+            (Complex::new(-0.16, 1.0405), (0.05, 0.0375), Subdivision::default()),
+            (Complex::new(-0.745, 0.113), (0.02, 0.015), Subdivision::new(4)),  // seahorse valley
+        ];
+        for (center, view_size, subdivision) in views {
+            let f = EscapeTimeFractal::new(
+                Mandelbrot::new(),
+                EscapeByCount::new(max_iter, 2.0),
+                PaletteColoring::new(Palette::grayscale(max_iter), max_iter),
+                (160, 120),
+                center,
+                view_size,
+            )
+            .with_subdivision(subdivision);
+            let expected = f.escape_values_par();
+            assert_eq!(f.escape_values_subdivided_par(subdivision), expected, "{}", center);
+            assert_eq!(f.colors_par(), f.colors_from_values_par(&expected), "{}", center);
+        }
+    }
+
+    #[test]
+    fn subdivision_finds_islands_inside_a_uniform_border() {
+        for radius in [0.05, 0.3, 0.6] {
+            let f = EscapeTimeFractal::new(
+                Island(radius),
+                EscapeByCount::new(MAX_ITER, 2.0),
+                PaletteColoring::new(Palette::grayscale(MAX_ITER), MAX_ITER),
+                (48, 40),
+                Complex::new(0.0, 0.0),
+                (2.0, 2.0),
+            )
+            .with_subdivision(Subdivision::default());
+            let expected = f.escape_values();
+            let (w, h) = f.resolution;
+            assert!(border((0, 0, w - 1, h - 1)).all(|(x, y)| expected[y * w + x] == MAX_ITER));
+            assert!(expected.contains(&1), "{}", radius);
+
+            assert_eq!(f.escape_values_subdivided(Subdivision::default()), expected, "{}", radius);
+            assert_eq!(f.escape_values_subdivided_par(Subdivision::default()), expected, "{}", radius);
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use std::fmt;
+
+use num_complex::{self, Complex};
+use crate::core::complex_dynamics::ComplexDynamics;
+use crate::util::types::Float;
+
+#[cfg(feature = "jit")]
+pub mod jit;
+
+/*
+実行時に文字列から作るComplexDynamics
+
+例:
+    let dynamics = Formula::new("z^3 - 0.5*conj(z) + c")?;
+    let julia = Formula::new("z^2 + k")?
+        .with_initial("c")?
+        .with_param("k", Complex::new(-0.8, 0.156));
+
+使える記法
+    変数: z(現在の値), c(描画点), i(虚数単位), pi
+    演算: + - * / ^ (^は右結合で単項マイナスより強い)
+    関数: abs(成分ごとの絶対値), mod(|z|), conj, re, im, exp, log, sqrt, sin, cos
+    それ以外の識別子は名前付きパラメータになる(未設定なら0)
+
+式はf64で評価する(JITもf64の機械語を生成するので結果を揃える)
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaParseError {
+    UnexpectedChar(char, usize),  // (文字, 位置)
+    UnexpectedToken(usize),  // 位置
+    UnexpectedEnd,
+    InvalidNumber(String),
+    UnknownFunction(String),
+    TooDeep(usize),  // 入れ子が深すぎる位置
+}
+
+impl fmt::Display for FormulaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaParseError::UnexpectedChar(ch, pos) => write!(f, "unexpected character '{}' at {}", ch, pos),
+            FormulaParseError::UnexpectedToken(pos) => write!(f, "unexpected token at {}", pos),
+            FormulaParseError::UnexpectedEnd => write!(f, "unexpected end of formula"),
+            FormulaParseError::InvalidNumber(s) => write!(f, "invalid number '{}'", s),
+            FormulaParseError::UnknownFunction(name) => write!(f, "unknown function '{}'", name),
+            FormulaParseError::TooDeep(pos) => write!(f, "formula nested too deeply at {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for FormulaParseError {}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Func {
+    Abs,
+    Mod,
+    Conj,
+    Re,
+    Im,
+    Exp,
+    Log,
+    Sqrt,
+    Sin,
+    Cos,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "abs" => Some(Func::Abs),
+            "mod" => Some(Func::Mod),
+            "conj" => Some(Func::Conj),
+            "re" => Some(Func::Re),
+            "im" => Some(Func::Im),
+            "exp" => Some(Func::Exp),
+            "log" => Some(Func::Log),
+            "sqrt" => Some(Func::Sqrt),
+            "sin" => Some(Func::Sin),
+            "cos" => Some(Func::Cos),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn apply(self, a: Complex<Float>) -> Complex<Float> {
+        match self {
+            Func::Abs => Complex::new(a.re.abs(), a.im.abs()),
+            Func::Mod => Complex::new(a.norm(), 0.0),
+            Func::Conj => a.conj(),
+            Func::Re => Complex::new(a.re, 0.0),
+            Func::Im => Complex::new(a.im, 0.0),
+            Func::Exp => a.exp(),
+            Func::Log => a.ln(),
+            Func::Sqrt => a.sqrt(),
+            Func::Sin => a.sin(),
+            Func::Cos => a.cos(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+impl BinOp {
+    pub(crate) fn apply(self, a: Complex<Float>, b: Complex<Float>) -> Complex<Float> {
+        match self {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div => a / b,
+            BinOp::Pow => complex_pow(a, b),
+        }
+    }
+}
+
+// 構文木．Param(i)はFormula::paramsのindex
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Const(Complex<Float>),
+    Z,
+    C,
+    Param(usize),
+    Neg(Box<Expr>),
+    Call(Func, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    PowInt(Box<Expr>, i32),  // 指数が整数定数の冪
+}
+
+impl Expr {
+    pub(crate) fn eval(&self, z: Complex<Float>, c: Complex<Float>, params: &[Complex<Float>]) -> Complex<Float> {
+        match self {
+            Expr::Const(v) => *v,
+            Expr::Z => z,
+            Expr::C => c,
+            Expr::Param(i) => params[*i],
+            Expr::Neg(a) => -a.eval(z, c, params),
+            Expr::Call(f, a) => f.apply(a.eval(z, c, params)),
+            Expr::PowInt(a, n) => a.eval(z, c, params).powi(*n),
+            Expr::Binary(op, a, b) => op.apply(a.eval(z, c, params), b.eval(z, c, params)),
+        }
+    }
+
+    // zについての多項式としての次数．超越関数などで決まらなければNone
+    fn degree(&self) -> Option<Float> {
+        match self {
+            Expr::Const(_) | Expr::C | Expr::Param(_) => Some(0.0),
+            Expr::Z => Some(1.0),
+            Expr::Neg(a) => a.degree(),
+            Expr::Call(Func::Abs | Func::Conj, a) => a.degree(),
+            Expr::Call(..) => None,
+            Expr::PowInt(a, n) => a.degree().map(|d| d * *n as Float),
+            Expr::Binary(op, a, b) => {
+                let (da, db) = (a.degree()?, b.degree()?);
+                match op {
+                    BinOp::Add | BinOp::Sub => Some(da.max(db)),
+                    BinOp::Mul => Some(da + db),
+                    BinOp::Div => Some(da - db),
+                    BinOp::Pow => match **b {
+                        Expr::Const(e) if e.im == 0.0 => Some(da * e.re),
+                        _ => None,
+                    },
+                }
+            }
+        }
+    }
+}
+
+// 0の冪はlogが発散するので別に扱う．0^0はPowIntのpowi(0)に合わせて1にする
+pub(crate) fn complex_pow(a: Complex<Float>, b: Complex<Float>) -> Complex<Float> {
+    if b == Complex::ZERO {
+        return Complex::ONE;
+    }
+    if a == Complex::ZERO {
+        return Complex::ZERO;
+    }
+    if b.im == 0.0 {
+        a.powf(b.re)
+    } else {
+        a.powc(b)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(Float),
+    Ident(String),
+    Op(char),  // + - * / ^ ( )
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, FormulaParseError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let ch = chars[pos];
+        if ch.is_whitespace() {
+            pos += 1;
+        } else if ch.is_ascii_digit() || ch == '.' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
+                pos += 1;
+            }
+            // 指数表記 1e-3
+            if pos < chars.len() && (chars[pos] == 'e' || chars[pos] == 'E') {
+                let mut end = pos + 1;
+                if end < chars.len() && (chars[end] == '+' || chars[end] == '-') {
+                    end += 1;
+                }
+                if end < chars.len() && chars[end].is_ascii_digit() {
+                    pos = end;
+                    while pos < chars.len() && chars[pos].is_ascii_digit() {
+                        pos += 1;
+                    }
+                }
+            }
+            let s: String = chars[start..pos].iter().collect();
+            let v = s.parse::<Float>().map_err(|_| FormulaParseError::InvalidNumber(s.clone()))?;
+            tokens.push((Token::Num(v), start));
+        } else if ch.is_alphabetic() || ch == '_' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            tokens.push((Token::Ident(chars[start..pos].iter().collect()), start));
+        } else if "+-*/^()".contains(ch) {
+            tokens.push((Token::Op(ch), pos));
+            pos += 1;
+        } else {
+            return Err(FormulaParseError::UnexpectedChar(ch, pos));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// 構文木の深さの上限．評価やJITのコンパイルも再帰するので，stack overflowの前にエラーにする
+const MAX_DEPTH: usize = 256;
+
+// 再帰下降パーサ．パラメータ名はparamsに追記していく
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,  // 作りかけの構文木の深さ
+    params: &'a mut Vec<(String, Complex<Float>)>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        t
+    }
+
+    fn error_here(&self) -> FormulaParseError {
+        match self.tokens.get(self.pos) {
+            Some((_, p)) => FormulaParseError::UnexpectedToken(*p),
+            None => FormulaParseError::UnexpectedEnd,
+        }
+    }
+
+    // 構文木を1段深くする．深さの上限を超えたらエラー
+    fn enter(&mut self) -> Result<(), FormulaParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            let pos = self.tokens.get(self.pos).or(self.tokens.last()).map_or(0, |(_, p)| *p);
+            return Err(FormulaParseError::TooDeep(pos));
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), FormulaParseError> {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error_here())
+        }
+    }
+
+    // expr = term (('+' | '-') term)*
+    // 左結合なので，演算子が1つ続くごとに構文木が1段深くなる
+    fn expr(&mut self) -> Result<Expr, FormulaParseError> {
+        let depth = self.depth;
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.enter()?;
+            self.pos += 1;
+            let rhs = self.term()?;
+            let op = if op == '+' { BinOp::Add } else { BinOp::Sub };
+            lhs = binary(op, lhs, rhs);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    // term = unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, FormulaParseError> {
+        let depth = self.depth;
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.enter()?;
+            self.pos += 1;
+            let rhs = self.unary()?;
+            let op = if op == '*' { BinOp::Mul } else { BinOp::Div };
+            lhs = binary(op, lhs, rhs);
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    // unary = '-' unary | power
+    // 再帰は必ずここを通るので，ここで深さを数える
+    fn unary(&mut self) -> Result<Expr, FormulaParseError> {
+        let depth = self.depth;
+        self.enter()?;
+        let e = if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            match self.unary()? {
+                Expr::Const(v) => Expr::Const(-v),
+                a => Expr::Neg(Box::new(a)),
+            }
+        } else {
+            self.power()?
+        };
+        self.depth = depth;
+        Ok(e)
+    }
+
+    // power = primary ('^' unary)?
+    fn power(&mut self) -> Result<Expr, FormulaParseError> {
+        let base = self.primary()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(binary(BinOp::Pow, base, exponent));
+        }
+        Ok(base)
+    }
+
+    // primary = number | ident | ident '(' expr ')' | '(' expr ')'
+    fn primary(&mut self) -> Result<Expr, FormulaParseError> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Expr::Const(Complex::new(v, 0.0))),
+            Some(Token::Op('(')) => {
+                let e = self.expr()?;
+                self.expect(')')?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::Op('(')) {
+                    let func = Func::from_name(&name)
+                        .ok_or(FormulaParseError::UnknownFunction(name))?;
+                    self.pos += 1;
+                    let arg = self.expr()?;
+                    self.expect(')')?;
+                    return Ok(match arg {
+                        Expr::Const(v) => Expr::Const(func.apply(v)),
+                        arg => Expr::Call(func, Box::new(arg)),
+                    });
+                }
+                Ok(match name.as_str() {
+                    "z" => Expr::Z,
+                    "c" => Expr::C,
+                    "i" => Expr::Const(Complex::I),
+                    "pi" => Expr::Const(Complex::new(std::f64::consts::PI, 0.0)),
+                    _ => Expr::Param(param_index(self.params, &name)),
+                })
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.error_here())
+            }
+        }
+    }
+}
+
+fn param_index(params: &mut Vec<(String, Complex<Float>)>, name: &str) -> usize {
+    match params.iter().position(|(n, _)| n == name) {
+        Some(i) => i,
+        None => {
+            params.push((name.to_string(), Complex::ZERO));
+            params.len() - 1
+        }
+    }
+}
+
+// 定数同士の演算は畳み込み，整数定数の冪はPowIntにする
+fn binary(op: BinOp, a: Expr, b: Expr) -> Expr {
+    if let (Expr::Const(x), Expr::Const(y)) = (&a, &b) {
+        return Expr::Const(op.apply(*x, *y));
+    }
+    if let (BinOp::Pow, Expr::Const(e)) = (op, &b) {
+        if e.im == 0.0 && e.re.fract() == 0.0 && e.re.abs() <= i32::MAX as Float {
+            return Expr::PowInt(Box::new(a), e.re as i32);
+        }
+    }
+    Expr::Binary(op, Box::new(a), Box::new(b))
+}
+
+fn parse(src: &str, params: &mut Vec<(String, Complex<Float>)>) -> Result<Expr, FormulaParseError> {
+    let tokens = tokenize(src)?;
+    let mut parser = Parser { tokens, pos: 0, depth: 0, params };
+    let expr = parser.expr()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error_here());
+    }
+    Ok(expr)
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Formula {
+    step: Expr,
+    initial: Expr,
+    params: Vec<(String, Complex<Float>)>,
+    param_values: Vec<Complex<Float>>,
+}
+
+impl Formula {
+    // 初期値z0 = 0で，1反復をstepの式で定める
+    pub fn new(step: &str) -> Result<Self, FormulaParseError> {
+        let mut params = Vec::new();
+        let step = parse(step, &mut params)?;
+        let param_values = params.iter().map(|&(_, v)| v).collect();
+        Ok(Self { step, initial: Expr::Const(Complex::ZERO), params, param_values })
+    }
+
+    // 初期値z0の式を設定する．Julia型ならwith_initial("c")
+    pub fn with_initial(mut self, initial: &str) -> Result<Self, FormulaParseError> {
+        self.initial = parse(initial, &mut self.params)?;
+        self.param_values = self.params.iter().map(|&(_, v)| v).collect();
+        Ok(self)
+    }
+
+    pub fn with_param(mut self, name: &str, value: Complex<Float>) -> Self {
+        self.set_param(name, value);
+        self
+    }
+
+    // まだ式に出てこない名前も保持しておき，後のwith_initialで使える
+    pub fn set_param(&mut self, name: &str, value: Complex<Float>) {
+        let i = param_index(&mut self.params, name);
+        self.params[i].1 = value;
+        self.param_values = self.params.iter().map(|&(_, v)| v).collect();
+    }
+
+    // (名前, 値)の一覧
+    pub fn params(&self) -> &[(String, Complex<Float>)] {
+        &self.params
+    }
+
+}
+
+impl ComplexDynamics for Formula {
+    fn initial_z(&self, c: Complex<Float>) -> Complex<Float> {
+        self.initial.eval(Complex::ZERO, c, &self.param_values)
+    }
+    fn step(&self, z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+        self.step.eval(z, c, &self.param_values)
+    }
+    // zの多項式として次数が決まれば使い，決まらなければ1(smoothingは整数の反復回数に戻る)
+    fn degree(&self) -> Float {
+        self.step.degree().unwrap_or(1.0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, z: Complex<Float>) -> Complex<Float> {
+        Formula::new(src).unwrap().step(z, Complex::new(0.25, -0.5))
+    }
+
+    fn assert_close(a: Complex<Float>, b: Complex<Float>) {
+        assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let z = Complex::new(1.5, 0.5);
+        let c = Complex::new(0.25, -0.5);
+        assert_close(eval("2*z + 1", z), 2.0 * z + 1.0);
+        assert_close(eval("2 + z*3", z), 2.0 + z * 3.0);
+        assert_close(eval("z - 1 - c", z), (z - 1.0) - c);
+        assert_close(eval("z / 2 / c", z), (z / 2.0) / c);
+        // ^は右結合
+        assert_close(eval("z^2^3", z), z.powi(8));
+        assert_close(eval("(z^2)^3", z), z.powi(6));
+        assert_close(eval("2*z^2", z), 2.0 * z * z);
+    }
+
+    #[test]
+    fn unary_minus() {
+        let z = Complex::new(1.5, 0.5);
+        // 単項マイナスは^より弱い
+        assert_close(eval("-z^2", z), -(z * z));
+        assert_close(eval("(-z)^2", z), z * z);
+        assert_close(eval("z^-1", z), z.inv());
+        assert_close(eval("--z", z), z);
+        assert_close(eval("2*-z", z), -2.0 * z);
+        assert_close(eval("-2 - -z", z), z - 2.0);
+    }
+
+    #[test]
+    fn functions_and_params() {
+        let z = Complex::new(-1.5, 0.5);
+        assert_close(eval("abs(z)^2 + c", z), Complex::new(1.5, 0.5).powi(2) + Complex::new(0.25, -0.5));
+        assert_close(eval("conj(z) + i*pi", z), z.conj() + Complex::I * std::f64::consts::PI);
+
+        let formula = Formula::new("z^2 + k").unwrap().with_param("k", Complex::new(0.0, 1.0));
+        assert_close(formula.step(z, Complex::ZERO), z * z + Complex::I);
+        assert_eq!(formula.params().len(), 1);
+    }
+
+    #[test]
+    fn zero_to_the_zero_is_one() {
+        // 定数の指数はPowInt，式の指数はcomplex_powで評価する
+        assert_eq!(eval("z^0", Complex::ZERO), Complex::ONE);
+        assert_eq!(eval("z^(z*0)", Complex::ZERO), Complex::ONE);
+        assert_eq!(eval("z^(c*0)", Complex::ZERO), Complex::ONE);
+        assert_eq!(eval("z^c", Complex::ZERO), Complex::ZERO);
+        assert_close(eval("z^(z*0)", Complex::new(1.5, 0.5)), Complex::ONE);
+    }
+
+    #[test]
+    fn degree_matches_the_builtin_presets() {
+        use crate::core::escape_evaluator::EscapeEvaluator;
+        use crate::core::escape_evaluator_presets::EscapeBySmoothCount;
+        use crate::core::complex_dynamics_presets::{Mandelbrot, MultibrotReal};
+
+        assert_eq!(Formula::new("z^2 + c").unwrap().degree(), 2.0);
+        assert_eq!(Formula::new("z^3 - 0.5*conj(z) + c").unwrap().degree(), 3.0);
+        assert_eq!(Formula::new("z + c").unwrap().degree(), 1.0);
+        assert_eq!(Formula::new("z^-2 + c").unwrap().degree(), 0.0);
+        assert_eq!(Formula::new("sin(z) + c").unwrap().degree(), 1.0);
+
+        let evaluator = EscapeBySmoothCount::new(100, 2.0);
+        let c = Complex::new(1.0, 0.0);
+        let linear: Float = evaluator.evaluate(&Formula::new("z + c").unwrap(), c);
+        let preset: Float = evaluator.evaluate(&MultibrotReal::new(1.0), c);
+        assert_eq!(linear, preset);
+        assert_eq!(linear, 3.0);
+
+        let c = Complex::new(0.3, 0.5);
+        let quadratic: Float = evaluator.evaluate(&Formula::new("z^2 + c").unwrap(), c);
+        let preset: Float = evaluator.evaluate(&Mandelbrot::new(), c);
+        assert!((quadratic - preset).abs() < 1e-12, "{} != {}", quadratic, preset);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |src| Formula::new(src).unwrap_err();
+        assert_eq!(error("z + $"), FormulaParseError::UnexpectedChar('$', 4));
+        assert_eq!(error("z +"), FormulaParseError::UnexpectedEnd);
+        assert_eq!(error("(z + 1"), FormulaParseError::UnexpectedEnd);
+        assert_eq!(error("z + 1)"), FormulaParseError::UnexpectedToken(5));
+        assert_eq!(error("z * * c"), FormulaParseError::UnexpectedToken(4));
+        assert_eq!(error("foo(z)"), FormulaParseError::UnknownFunction("foo".to_string()));
+        assert_eq!(error("1.2.3"), FormulaParseError::InvalidNumber("1.2.3".to_string()));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let parens = format!("{}z{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(matches!(Formula::new(&parens), Err(FormulaParseError::TooDeep(_))));
+        let minus = format!("{}z", "-".repeat(10_000));
+        assert!(matches!(Formula::new(&minus), Err(FormulaParseError::TooDeep(_))));
+        let sum = format!("z{}", "+z".repeat(10_000));
+        assert!(matches!(Formula::new(&sum), Err(FormulaParseError::TooDeep(_))));
+        let call = format!("{}z{}", "sin(".repeat(10_000), ")".repeat(10_000));
+        assert!(matches!(Formula::new(&call), Err(FormulaParseError::TooDeep(_))));
+
+        // 上限までの入れ子は通る
+        let shallow = format!("{}z{}", "(".repeat(50), ")".repeat(50));
+        assert!(Formula::new(&shallow).is_ok());
+        let polynomial = (1..=20).map(|n| format!("{}*z^{}", n, n)).collect::<Vec<_>>().join(" + ");
+        assert!(Formula::new(&polynomial).is_ok());
+    }
+}
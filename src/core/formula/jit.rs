@@ -0,0 +1,417 @@
+use std::fmt;
+
+use cranelift_codegen::ir::{types, AbiParam, InstBuilder, MemFlags, StackSlotData, StackSlotKind, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use num_complex::{self, Complex};
+
+use crate::core::complex_dynamics::ComplexDynamics;
+use crate::core::formula::{complex_pow, BinOp, Expr, Formula, Func};
+use crate::util::types::Float;
+
+/*
+FormulaをCraneliftで機械語にコンパイルしたComplexDynamics
+四則演算・整数冪・abs/conj/re/im/modはインライン展開し，
+exp/log/sqrt/sin/cosと実数・複素数冪はRust側の関数を呼ぶ
+
+例:
+    let dynamics = JitFormula::new(Formula::new("z^3 - 0.5*conj(z) + c")?)?;
+*/
+
+#[derive(Debug, Clone)]
+pub struct FormulaJitError(String);
+
+impl fmt::Display for FormulaJitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to compile formula: {}", self.0)
+    }
+}
+
+impl std::error::Error for FormulaJitError {}
+
+
+// コンパイルされた関数: (z.re, z.im, c.re, c.im, params, out)．outに結果の(re, im)を書く
+type CompiledFn = unsafe extern "C" fn(Float, Float, Float, Float, *const Float, *mut Float);
+
+pub struct JitFormula {
+    formula: Formula,
+    step_fn: CompiledFn,
+    initial_fn: CompiledFn,
+    module: Option<JITModule>,
+}
+
+// moduleはfinalize後に書き換えず，Dropで解放するだけなのでスレッド間で共有してよい
+unsafe impl Send for JitFormula {}
+unsafe impl Sync for JitFormula {}
+
+impl JitFormula {
+    pub fn new(formula: Formula) -> Result<Self, FormulaJitError> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(|e| FormulaJitError(e.to_string()))?;
+        let isa = cranelift_native::builder()
+            .map_err(|e| FormulaJitError(e.to_string()))?
+            .finish(settings::Flags::new(flags))
+            .map_err(|e| FormulaJitError(e.to_string()))?;
+
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        for (name, ptr) in HELPERS {
+            builder.symbol(name, ptr);
+        }
+        let mut module = JITModule::new(builder);
+
+        let step_id = compile(&mut module, "step", &formula.step)?;
+        let initial_id = compile(&mut module, "initial", &formula.initial)?;
+        module.finalize_definitions().map_err(|e| FormulaJitError(e.to_string()))?;
+
+        // シグネチャはcompileで宣言したものと一致する
+        let step_fn = unsafe {
+            std::mem::transmute::<*const u8, CompiledFn>(module.get_finalized_function(step_id))
+        };
+        let initial_fn = unsafe {
+            std::mem::transmute::<*const u8, CompiledFn>(module.get_finalized_function(initial_id))
+        };
+
+        Ok(Self { formula, step_fn, initial_fn, module: Some(module) })
+    }
+
+    // パラメータは実行時に参照するので再コンパイルは不要
+    pub fn with_param(mut self, name: &str, value: Complex<Float>) -> Self {
+        self.set_param(name, value);
+        self
+    }
+
+    pub fn set_param(&mut self, name: &str, value: Complex<Float>) {
+        self.formula.set_param(name, value);
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    #[inline]
+    fn call(&self, f: CompiledFn, z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+        let mut out = [0.0; 2];
+        // Complexは#[repr(C)]なので[re, im, re, im, ...]と並ぶ
+        let params = self.formula.param_values.as_ptr() as *const Float;
+        unsafe { f(z.re, z.im, c.re, c.im, params, out.as_mut_ptr()) };
+        Complex::new(out[0], out[1])
+    }
+}
+
+impl Drop for JitFormula {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // step_fn, initial_fnはselfと一緒に破棄されるので，以後呼ばれることはない
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+impl fmt::Debug for JitFormula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JitFormula").field("formula", &self.formula).finish()
+    }
+}
+
+impl ComplexDynamics for JitFormula {
+    fn initial_z(&self, c: Complex<Float>) -> Complex<Float> {
+        self.call(self.initial_fn, Complex::ZERO, c)
+    }
+    fn step(&self, z: Complex<Float>, c: Complex<Float>) -> Complex<Float> {
+        self.call(self.step_fn, z, c)
+    }
+    fn degree(&self) -> Float {
+        self.formula.degree()
+    }
+}
+
+
+/* ===== JITから呼ぶRust側の関数 ===== */
+
+unsafe fn write_out(out: *mut Float, v: Complex<Float>) {
+    *out = v.re;
+    *out.add(1) = v.im;
+}
+
+extern "C" fn helper_exp(re: Float, im: Float, out: *mut Float) {
+    unsafe { write_out(out, Func::Exp.apply(Complex::new(re, im))) }
+}
+
+extern "C" fn helper_log(re: Float, im: Float, out: *mut Float) {
+    unsafe { write_out(out, Func::Log.apply(Complex::new(re, im))) }
+}
+
+extern "C" fn helper_sqrt(re: Float, im: Float, out: *mut Float) {
+    unsafe { write_out(out, Func::Sqrt.apply(Complex::new(re, im))) }
+}
+
+extern "C" fn helper_sin(re: Float, im: Float, out: *mut Float) {
+    unsafe { write_out(out, Func::Sin.apply(Complex::new(re, im))) }
+}
+
+extern "C" fn helper_cos(re: Float, im: Float, out: *mut Float) {
+    unsafe { write_out(out, Func::Cos.apply(Complex::new(re, im))) }
+}
+
+extern "C" fn helper_pow(a_re: Float, a_im: Float, b_re: Float, b_im: Float, out: *mut Float) {
+    unsafe { write_out(out, complex_pow(Complex::new(a_re, a_im), Complex::new(b_re, b_im))) }
+}
+
+const HELPERS: [(&str, *const u8); 6] = [
+    ("etfra_exp", helper_exp as *const u8),
+    ("etfra_log", helper_log as *const u8),
+    ("etfra_sqrt", helper_sqrt as *const u8),
+    ("etfra_sin", helper_sin as *const u8),
+    ("etfra_cos", helper_cos as *const u8),
+    ("etfra_pow", helper_pow as *const u8),
+];
+
+
+/* ===== コード生成 ===== */
+
+// exprを(z.re, z.im, c.re, c.im, params, out)を受け取る関数としてmoduleに定義する
+fn compile(module: &mut JITModule, name: &str, expr: &Expr) -> Result<FuncId, FormulaJitError> {
+    let ptr = module.target_config().pointer_type();
+    let mut ctx = module.make_context();
+    for _ in 0..4 {
+        ctx.func.signature.params.push(AbiParam::new(types::F64));
+    }
+    ctx.func.signature.params.push(AbiParam::new(ptr));
+    ctx.func.signature.params.push(AbiParam::new(ptr));
+
+    let mut fn_ctx = FunctionBuilderContext::new();
+    {
+        let mut b = FunctionBuilder::new(&mut ctx.func, &mut fn_ctx);
+        let block = b.create_block();
+        b.append_block_params_for_function_params(block);
+        b.switch_to_block(block);
+        b.seal_block(block);
+
+        let args = b.block_params(block).to_vec();
+        let mut gen = CodeGen {
+            module,
+            z: (args[0], args[1]),
+            c: (args[2], args[3]),
+            params: args[4],
+            ptr,
+        };
+        let (re, im) = gen.expr(&mut b, expr)?;
+
+        let out = args[5];
+        b.ins().store(MemFlags::trusted(), re, out, 0);
+        b.ins().store(MemFlags::trusted(), im, out, 8);
+        b.ins().return_(&[]);
+        b.finalize();
+    }
+
+    let id = module
+        .declare_function(name, Linkage::Local, &ctx.func.signature)
+        .map_err(|e| FormulaJitError(e.to_string()))?;
+    module
+        .define_function(id, &mut ctx)
+        .map_err(|e| FormulaJitError(e.to_string()))?;
+    module.clear_context(&mut ctx);
+
+    Ok(id)
+}
+
+type CValue = (Value, Value);
+
+struct CodeGen<'a> {
+    module: &'a mut JITModule,
+    z: CValue,
+    c: CValue,
+    params: Value,
+    ptr: types::Type,
+}
+
+impl CodeGen<'_> {
+    fn expr(&mut self, b: &mut FunctionBuilder, expr: &Expr) -> Result<CValue, FormulaJitError> {
+        Ok(match expr {
+            Expr::Const(v) => (b.ins().f64const(v.re), b.ins().f64const(v.im)),
+            Expr::Z => self.z,
+            Expr::C => self.c,
+            Expr::Param(i) => {
+                let offset = (i * 2 * std::mem::size_of::<Float>()) as i32;
+                let re = b.ins().load(types::F64, MemFlags::trusted(), self.params, offset);
+                let im = b.ins().load(types::F64, MemFlags::trusted(), self.params, offset + 8);
+                (re, im)
+            }
+            Expr::Neg(a) => {
+                let (re, im) = self.expr(b, a)?;
+                (b.ins().fneg(re), b.ins().fneg(im))
+            }
+            Expr::Call(func, a) => {
+                let a = self.expr(b, a)?;
+                self.call(b, *func, a)?
+            }
+            Expr::PowInt(a, n) => {
+                let a = self.expr(b, a)?;
+                self.powi(b, a, *n)
+            }
+            Expr::Binary(op, a, rhs) => {
+                let a = self.expr(b, a)?;
+                let c = self.expr(b, rhs)?;
+                match op {
+                    BinOp::Add => (b.ins().fadd(a.0, c.0), b.ins().fadd(a.1, c.1)),
+                    BinOp::Sub => (b.ins().fsub(a.0, c.0), b.ins().fsub(a.1, c.1)),
+                    BinOp::Mul => mul(b, a, c),
+                    BinOp::Div => div(b, a, c),
+                    BinOp::Pow => self.call_helper(b, "etfra_pow", &[a.0, a.1, c.0, c.1])?,
+                }
+            }
+        })
+    }
+
+    fn call(&mut self, b: &mut FunctionBuilder, func: Func, (re, im): CValue) -> Result<CValue, FormulaJitError> {
+        let zero = b.ins().f64const(0.0);
+        Ok(match func {
+            Func::Abs => (b.ins().fabs(re), b.ins().fabs(im)),
+            Func::Conj => (re, b.ins().fneg(im)),
+            Func::Re => (re, zero),
+            Func::Im => (im, zero),
+            Func::Mod => {
+                let rr = b.ins().fmul(re, re);
+                let ii = b.ins().fmul(im, im);
+                let norm_sqr = b.ins().fadd(rr, ii);
+                (b.ins().sqrt(norm_sqr), zero)
+            }
+            Func::Exp => self.call_helper(b, "etfra_exp", &[re, im])?,
+            Func::Log => self.call_helper(b, "etfra_log", &[re, im])?,
+            Func::Sqrt => self.call_helper(b, "etfra_sqrt", &[re, im])?,
+            Func::Sin => self.call_helper(b, "etfra_sin", &[re, im])?,
+            Func::Cos => self.call_helper(b, "etfra_cos", &[re, im])?,
+        })
+    }
+
+    // 引数のf64列とoutへのポインタを渡してRust側の関数を呼ぶ
+    fn call_helper(&mut self, b: &mut FunctionBuilder, name: &str, args: &[Value]) -> Result<CValue, FormulaJitError> {
+        let mut sig = self.module.make_signature();
+        for _ in args {
+            sig.params.push(AbiParam::new(types::F64));
+        }
+        sig.params.push(AbiParam::new(self.ptr));
+
+        let id = self.module
+            .declare_function(name, Linkage::Import, &sig)
+            .map_err(|e| FormulaJitError(e.to_string()))?;
+        let callee = self.module.declare_func_in_func(id, b.func);
+
+        let slot = b.create_sized_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, 16, 3));
+        let out = b.ins().stack_addr(self.ptr, slot, 0);
+        let mut call_args = args.to_vec();
+        call_args.push(out);
+        b.ins().call(callee, &call_args);
+
+        let re = b.ins().stack_load(types::F64, slot, 0);
+        let im = b.ins().stack_load(types::F64, slot, 8);
+        Ok((re, im))
+    }
+
+    // 繰り返し二乗法をコンパイル時に展開する．負の冪は最後に逆数をとる
+    fn powi(&mut self, b: &mut FunctionBuilder, a: CValue, n: i32) -> CValue {
+        let one = b.ins().f64const(1.0);
+        let zero = b.ins().f64const(0.0);
+        let mut result = (one, zero);
+        let mut base = a;
+        let mut k = n.unsigned_abs();
+        let mut first = true;
+        while k > 0 {
+            if k & 1 == 1 {
+                result = if first { base } else { mul(b, result, base) };
+                first = false;
+            }
+            k >>= 1;
+            if k > 0 {
+                base = mul(b, base, base);
+            }
+        }
+        if n < 0 {
+            result = div(b, (one, zero), result);
+        }
+        result
+    }
+}
+
+fn mul(b: &mut FunctionBuilder, (ar, ai): CValue, (br, bi): CValue) -> CValue {
+    let rr = b.ins().fmul(ar, br);
+    let ii = b.ins().fmul(ai, bi);
+    let ri = b.ins().fmul(ar, bi);
+    let ir = b.ins().fmul(ai, br);
+    (b.ins().fsub(rr, ii), b.ins().fadd(ri, ir))
+}
+
+// num_complexと同じ (a * conj(b)) / |b|^2
+fn div(b: &mut FunctionBuilder, (ar, ai): CValue, (br, bi): CValue) -> CValue {
+    let brr = b.ins().fmul(br, br);
+    let bii = b.ins().fmul(bi, bi);
+    let norm_sqr = b.ins().fadd(brr, bii);
+    let rr = b.ins().fmul(ar, br);
+    let ii = b.ins().fmul(ai, bi);
+    let ir = b.ins().fmul(ai, br);
+    let ri = b.ins().fmul(ar, bi);
+    let re = b.ins().fadd(rr, ii);
+    let im = b.ins().fsub(ir, ri);
+    (b.ins().fdiv(re, norm_sqr), b.ins().fdiv(im, norm_sqr))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // JITとインタプリタで同じ式を評価し，丸め誤差の範囲で一致することを確かめる
+    fn assert_matches_interpreter(formula: Formula) {
+        let jit = JitFormula::new(formula.clone()).unwrap();
+        for y in -4..=4 {
+            for x in -4..=4 {
+                let z = Complex::new(0.37 * x as Float + 0.01, 0.29 * y as Float - 0.02);
+                let c = Complex::new(-0.11 * y as Float, 0.23 * x as Float);
+                for (expected, actual) in [
+                    (formula.step(z, c), jit.step(z, c)),
+                    (formula.initial_z(c), jit.initial_z(c)),
+                ] {
+                    let tolerance = 1e-12 * expected.norm().max(1.0);
+                    assert!(
+                        (expected - actual).norm() <= tolerance,
+                        "{:?}: z = {}, c = {}: {} != {}", formula, z, c, expected, actual,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jit_matches_interpreter() {
+        for src in [
+            "z^2 + c",
+            "z^3 - 0.5*conj(z) + c",
+            "abs(z)^2 + c",
+            "z^-2 + c / (z + 1)",
+            "-z^5 + 2*z^2 - i*c",
+            "re(z)*im(z) + mod(z) + c",
+            "exp(z) + log(z + 2) + sqrt(z) + c",
+            "sin(z)*cos(c) + z^1.5",
+            "z^(1 + i) + c",
+        ] {
+            assert_matches_interpreter(Formula::new(src).unwrap());
+        }
+    }
+
+    #[test]
+    fn jit_uses_initial_and_params() {
+        let formula = Formula::new("z^2 + k")
+            .unwrap()
+            .with_initial("c + k")
+            .unwrap()
+            .with_param("k", Complex::new(-0.8, 0.156));
+        assert_matches_interpreter(formula.clone());
+
+        // パラメータの変更は再コンパイルなしで反映される
+        let jit = JitFormula::new(formula).unwrap().with_param("k", Complex::new(0.3, 0.0));
+        let z = Complex::new(0.5, 0.5);
+        assert_eq!(jit.step(z, Complex::ZERO), z * z + Complex::new(0.3, 0.0));
+    }
+}
@@ -0,0 +1,416 @@
+use crate::util::big_float::BigFloat;
+use crate::util::double_double::DoubleDouble;
+use crate::util::color::Color;
+use crate::util::real::Real;
+use crate::util::types::Float;
+use crate::core::complex_dynamics::ComplexDynamics;
+use crate::core::coloring::Coloring;
+use crate::core::escape_evaluator_presets::EscapeResult;
+use crate::core::precision::{Precision, ViewFloat};
+use crate::core::bla::{BlaStep, BlaTable};
+
+use rayon::prelude::*;
+use num_complex::{self, Complex};
+
+// BLAで許す線形近似の相対誤差の既定値
+const DEFAULT_BLA_EPSILON: Float = 1e-12;
+
+// 摂動論(perturbation)で描画できる力学系．z0 = 0から始まるMandelbrot型を仮定する
+// 描画点c = C + dcの軌道を参照点Cの軌道Z_nからのずれδ_n = z_n - Z_nとして倍精度で反復する
+// 参照軌道はPrecisionのどの精度でも計算できる必要がある
+pub trait PerturbableDynamics:
+    ComplexDynamics<Float>
+    + ComplexDynamics<DoubleDouble>
+    + ComplexDynamics<BigFloat<2>>
+    + ComplexDynamics<BigFloat<4>>
+    + ComplexDynamics<BigFloat<8>>
+    + ComplexDynamics<BigFloat<16>>
+{
+    // δ_nからδ_{n+1}を求める．z_refは参照軌道のZ_n
+    fn step_delta(&self, z_ref: Complex<Float>, delta: Complex<Float>, dc: Complex<Float>) -> Complex<Float>;
+
+    // Z_nの周りでstep_deltaを線形化した δ_{n+1} ≈ a δ_n + b dc
+    // radiusは非線形項が線形項に比べて無視できる|δ|の目安(相対誤差1のときの値)
+    // Noneなら近似で反復を省略しない
+    fn linearize(&self, _z_ref: Complex<Float>) -> Option<BlaStep> {
+        None
+    }
+}
+
+// |c + d| - |c|を桁落ちなしに求める．absを含む式のδを場合分けで計算するのに使う
+pub fn diffabs(c: Float, d: Float) -> Float {
+    let cd = c + d;
+    match (c >= 0.0, cd >= 0.0) {
+        (true, true) => d,
+        (true, false) => -(2.0 * c + d),
+        (false, true) => 2.0 * c + d,
+        (false, false) => -d,
+    }
+}
+
+
+// 中心で1本だけ高精度の参照軌道を計算し，各pixelは倍精度のずれだけを反復する
+// 描画点が参照軌道より0に近づいたとき(|z| < |δ|)や参照軌道が尽きたときは，
+// 参照軌道の先頭に乗り換えて(rebase)精度の劣化(glitch)を避ける
+pub struct PerturbationFractal<D, C>
+where
+    D: PerturbableDynamics,
+    C: Coloring<EscapeResult>,
+{
+    pub dynamics: D,  // 力学系の定義
+    pub coloring: C,  // 色付け
+    pub max_iter: usize,
+    pub escape_radius: Float,
+    pub resolution: (usize, usize),  // 描画画像サイズ(w, h)
+    pub center: Complex<ViewFloat>,  // 描画の中心．参照点になる
+    pub view_size: (Float, Float),  // 描画する範囲(re, im)
+    pub bla_epsilon: Float,  // BLAで許す相対誤差．0なら反復を省略しない
+}
+
+impl<D, C> PerturbationFractal<D, C>
+where
+    D: PerturbableDynamics + Sync,
+    C: Coloring<EscapeResult> + Sync,
+{
+    pub fn new(
+        dynamics: D,
+        coloring: C,
+        max_iter: usize,
+        escape_radius: Float,
+        resolution: (usize, usize),
+        center: Complex<ViewFloat>,
+        view_size: (Float, Float),
+    ) -> Self {
+        PerturbationFractal {
+            dynamics,
+            coloring,
+            max_iter,
+            escape_radius,
+            resolution,
+            center,
+            view_size,
+            bla_epsilon: DEFAULT_BLA_EPSILON,
+        }
+    }
+
+    pub fn with_bla_epsilon(mut self, epsilon: Float) -> Self {
+        self.bla_epsilon = epsilon;
+        self
+    }
+
+    // 参照軌道の計算に使う精度
+    pub fn precision(&self) -> Precision {
+        Precision::from_scale(self.view_size.0 / self.resolution.0 as Float)
+    }
+
+    // 1pixelの大きさに見合う精度で参照軌道Z_0, Z_1, ...を計算する．脱出した点で打ち切る
+    pub fn reference_orbit(&self) -> Vec<Complex<Float>> {
+        match self.precision() {
+            Precision::F64 => self.reference_orbit_with::<Float>(),
+            Precision::DoubleDouble => self.reference_orbit_with::<DoubleDouble>(),
+            Precision::Big128 => self.reference_orbit_with::<BigFloat<2>>(),
+            Precision::Big256 => self.reference_orbit_with::<BigFloat<4>>(),
+            Precision::Big512 => self.reference_orbit_with::<BigFloat<8>>(),
+            Precision::Big1024 => self.reference_orbit_with::<BigFloat<16>>(),
+        }
+    }
+
+    fn reference_orbit_with<T: Real>(&self) -> Vec<Complex<Float>>
+    where
+        D: ComplexDynamics<T>,
+    {
+        let c = Complex::new(self.center.re.to_real::<T>(), self.center.im.to_real::<T>());
+        let radius_sqr = self.escape_radius * self.escape_radius;
+        let mut z = self.dynamics.initial_z(c);
+        let mut orbit = Vec::with_capacity(self.max_iter + 1);
+        orbit.push(Complex::new(z.re.to_f64(), z.im.to_f64()));
+
+        for _ in 0..self.max_iter {
+            z = self.dynamics.step(z, c);
+            let z64 = Complex::new(z.re.to_f64(), z.im.to_f64());
+            orbit.push(z64);
+            if z64.norm_sqr() > radius_sqr {
+                break;
+            }
+        }
+
+        orbit
+    }
+
+    // 参照軌道から反復を省略するための表を作る
+    pub fn bla_table(&self, orbit: &[Complex<Float>]) -> BlaTable {
+        if self.bla_epsilon <= 0.0 {
+            return BlaTable::default();
+        }
+
+        // Z_1からZ_{last-1}までの1回分の近似．途中で線形化できない点があればそこまで
+        let steps = orbit
+            .iter()
+            .skip(1)
+            .take(orbit.len().saturating_sub(2))
+            .map_while(|&z| self.dynamics.linearize(z))
+            .collect();
+        let dc_max = 0.5 * Complex::new(self.view_size.0, self.view_size.1).norm();
+
+        BlaTable::new(steps, dc_max, self.bla_epsilon)
+    }
+
+    // centerからのずれ
+    fn pixel_to_delta(&self, point: (usize, usize)) -> Complex<Float> {
+        let (x, y) = point;
+        let (w, h) = self.resolution;
+        let (view_w, view_h) = self.view_size;
+
+        let re = (x as Float / w as Float - 0.5) * view_w;
+        let im = (0.5 - y as Float / h as Float) * view_h;
+
+        Complex { re, im }
+    }
+
+    fn evaluate(&self, orbit: &[Complex<Float>], bla: &BlaTable, dc: Complex<Float>) -> EscapeResult {
+        let radius_sqr = self.escape_radius * self.escape_radius;
+        let last = orbit.len() - 1;
+        let mut delta = Complex::ZERO;
+        let mut m = 0;
+        let mut z = Complex::ZERO;
+        let mut min_norm_sqr = Float::INFINITY;
+        let mut i = 0;
+
+        while i < self.max_iter {
+            // 線形近似が効く範囲では何回分かをまとめて進める
+            let limit = (last - m).min(self.max_iter - i);
+            match bla.lookup(m, delta.norm(), limit) {
+                Some((step, len)) => {
+                    delta = step.apply(delta, dc);
+                    m += len;
+                    i += len;
+                }
+                None => {
+                    delta = self.dynamics.step_delta(orbit[m], delta, dc);
+                    m += 1;
+                    i += 1;
+                }
+            }
+            z = orbit[m] + delta;
+
+            let norm_sqr = z.norm_sqr();
+            min_norm_sqr = min_norm_sqr.min(norm_sqr);
+            if norm_sqr > radius_sqr {
+                return EscapeResult {
+                    iter: i,
+                    z,
+                    escaped: true,
+                    min_norm: min_norm_sqr.sqrt(),
+                    period: None,
+                };
+            }
+
+            // Z_0 = 0なのでδ = zとすれば参照軌道の先頭からやり直せる
+            if norm_sqr < delta.norm_sqr() || m == last {
+                delta = z;
+                m = 0;
+            }
+        }
+
+        EscapeResult {
+            iter: self.max_iter,
+            z,
+            escaped: false,
+            min_norm: min_norm_sqr.sqrt(),
+            period: None,
+        }
+    }
+
+    pub fn escape_values(&self) -> Vec<EscapeResult> {
+        let (w, h) = self.resolution;
+        let orbit = self.reference_orbit();
+        let bla = self.bla_table(&orbit);
+
+        (0..w * h)
+            .map(|i| self.evaluate(&orbit, &bla, self.pixel_to_delta((i % w, i / w))))
+            .collect()
+    }
+
+    pub fn escape_values_par(&self) -> Vec<EscapeResult> {
+        let (w, h) = self.resolution;
+        let orbit = self.reference_orbit();
+        let bla = self.bla_table(&orbit);
+
+        (0..w * h)
+            .into_par_iter()
+            .map(|i| self.evaluate(&orbit, &bla, self.pixel_to_delta((i % w, i / w))))
+            .collect()
+    }
+
+    pub fn colors_from_values(&self, values: &[EscapeResult]) -> Vec<Color> {
+        values
+            .iter()
+            .map(|&v| self.coloring.color(v))
+            .collect()
+    }
+
+    pub fn colors_from_values_par(&self, values: &[EscapeResult]) -> Vec<Color> {
+        values
+            .par_iter()
+            .map(|&v| self.coloring.color(v))
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::complex_dynamics_presets::{
+        Buffalo, BurningShip, Celtic, Mandelbrot, PerpendicularBurningShip, PerpendicularMandelbrot, Tricorn,
+    };
+    use crate::core::coloring_presets::PaletteColoring;
+    use crate::util::palette::Palette;
+
+    const SIZE: usize = 32;
+
+    fn fractal<D>(dynamics: D, center: (Float, Float), view: Float, max_iter: usize) -> PerturbationFractal<D, PaletteColoring>
+    where
+        D: PerturbableDynamics + Sync,
+    {
+        PerturbationFractal::new(
+            dynamics,
+            PaletteColoring::new(Palette::grayscale(16), max_iter),
+            max_iter,
+            2.0,
+            (SIZE, SIZE),
+            Complex::new(ViewFloat::from_f64(center.0), ViewFloat::from_f64(center.1)),
+            (view, view),
+        )
+        .with_bla_epsilon(0.0)
+    }
+
+    // 各pixelの点をBigFloat<4>で直接反復した反復回数と最後のz．shiftはpixel単位のずれ
+    fn direct<D>(fractal: &PerturbationFractal<D, PaletteColoring>, shift: Complex<Float>) -> Vec<(usize, Complex<Float>)>
+    where
+        D: PerturbableDynamics + Sync,
+    {
+        type T = BigFloat<4>;
+        let pixel = fractal.view_size.0 / SIZE as Float;
+        let center = Complex::new(fractal.center.re.to_real::<T>(), fractal.center.im.to_real::<T>());
+        (0..SIZE * SIZE)
+            .map(|i| {
+                let dc = fractal.pixel_to_delta((i % SIZE, i / SIZE)) + shift * pixel;
+                let c = center + Complex::new(T::from_f64(dc.re), T::from_f64(dc.im));
+                let mut z = ComplexDynamics::<T>::initial_z(&fractal.dynamics, c);
+                for n in 1..=fractal.max_iter {
+                    z = fractal.dynamics.step(z, c);
+                    let z64 = Complex::new(z.re.to_f64(), z.im.to_f64());
+                    if z64.norm_sqr() > 4.0 {
+                        return (n, z64);
+                    }
+                }
+                (fractal.max_iter, Complex::new(z.re.to_f64(), z.im.to_f64()))
+            })
+            .collect()
+    }
+
+    // 直接反復と同じ反復回数になったpixelの数と，比較したpixelの数
+    // 境界の近くや有界なカオス的軌道では，丸め誤差程度のずれで結果が変わるので，
+    // cを少しずらしても反復回数と最後のzが変わらないpixelだけを比べる
+    fn compare<D>(fractal: &PerturbationFractal<D, PaletteColoring>) -> (usize, usize)
+    where
+        D: PerturbableDynamics + Sync,
+    {
+        let expected = direct(fractal, Complex::ZERO);
+        let shifted: Vec<_> = [(1e-6, 0.0), (-1e-6, 0.0), (0.0, 1e-6), (0.0, -1e-6)]
+            .iter()
+            .map(|&(re, im)| direct(fractal, Complex::new(re, im)))
+            .collect();
+        let stable: Vec<usize> = (0..SIZE * SIZE)
+            .filter(|&i| {
+                let (n, z) = expected[i];
+                shifted.iter().all(|s| s[i].0 == n && (s[i].1 - z).norm() < 1e-6 * z.norm().max(1.0))
+            })
+            .collect();
+        let values = fractal.escape_values();
+        let matched = stable.iter().filter(|&&i| values[i].iter == expected[i].0).count();
+        (matched, stable.len())
+    }
+
+    #[test]
+    fn perturbation_matches_direct_iteration() {
+        // 浅い描画(f64の参照軌道)と，Misiurewicz点c = iの周りの深い描画
+        for (center, view, precision) in [
+            ((-0.75, 0.1), 1e-3, Precision::F64),
+            ((0.0, 1.0), 1e-20, Precision::DoubleDouble),
+            ((0.0, 1.0), 1e-30, Precision::Big128),
+        ] {
+            let fractal = fractal(Mandelbrot::new(), center, view, 1000);
+            assert_eq!(fractal.precision(), precision);
+            let (matched, compared) = compare(&fractal);
+            assert!(compared > SIZE * SIZE / 2, "{:?}: only {} stable pixels", center, compared);
+            assert_eq!(matched, compared, "{:?} at {}", center, view);
+        }
+    }
+
+    #[test]
+    fn bla_agrees_with_plain_perturbation() {
+        for (center, view) in [((-0.75, 0.1), 1e-3), ((0.0, 1.0), 1e-20), ((0.0, 1.0), 1e-30)] {
+            let plain = fractal(Mandelbrot::new(), center, view, 1000);
+            let bla = fractal(Mandelbrot::new(), center, view, 1000).with_bla_epsilon(DEFAULT_BLA_EPSILON);
+            let (expected, actual) = (plain.escape_values(), bla.escape_values());
+            // 近似の誤差で反復回数がずれるpixelは1%まで，ずれも1回までとする
+            let differing: Vec<_> = expected
+                .iter()
+                .zip(&actual)
+                .filter(|(e, a)| e.iter != a.iter)
+                .map(|(e, a)| e.iter.abs_diff(a.iter))
+                .collect();
+            assert!(differing.len() <= SIZE * SIZE / 100, "{:?}: {} pixels differ", center, differing.len());
+            assert!(differing.iter().all(|&d| d <= 1), "{:?}: {:?}", center, differing);
+        }
+
+        // 深い描画では実際に反復をまとめて省略している
+        let bla = fractal(Mandelbrot::new(), (0.0, 1.0), 1e-20, 1000).with_bla_epsilon(DEFAULT_BLA_EPSILON);
+        let table = bla.bla_table(&bla.reference_orbit());
+        assert!(matches!(table.lookup(1, 0.0, usize::MAX), Some((_, len)) if len > 1));
+    }
+
+    fn assert_matches_direct<D>(fractal: &PerturbationFractal<D, PaletteColoring>, min_compared: usize)
+    where
+        D: PerturbableDynamics + Sync,
+    {
+        let (matched, compared) = compare(fractal);
+        assert!(compared >= min_compared, "only {} stable pixels", compared);
+        assert_eq!(matched, compared, "at {}", fractal.view_size.0);
+    }
+
+    #[test]
+    fn burning_ship_perturbation_matches_direct_iteration() {
+        // 実軸の近くでは|Im z|の折り返しが頻繁に起こる
+        assert_matches_direct(&fractal(BurningShip::new(), (-1.862, -0.0009), 1e-3, 1000), 100);
+        let deep = fractal(BurningShip::new(), (-2.0, 0.0), 1e-25, 1000);
+        assert_matches_direct(&deep, SIZE * SIZE / 2);
+        assert_matches_direct(&deep.with_bla_epsilon(DEFAULT_BLA_EPSILON), SIZE * SIZE / 2);
+    }
+
+    #[test]
+    fn abs_formulas_match_direct_iteration() {
+        let min = SIZE * SIZE / 2;
+        assert_matches_direct(&fractal(Tricorn::new(), (-2.0, 0.0), 1e-25, 1000), min);
+        assert_matches_direct(&fractal(Celtic::new(), (-2.0, 0.0), 1e-25, 1000), min);
+        assert_matches_direct(&fractal(Buffalo::new(), (-2.0, 0.0), 1e-25, 1000), min);
+        assert_matches_direct(&fractal(PerpendicularMandelbrot::new(), (-1.862, -0.0009), 1e-3, 1000), min);
+        assert_matches_direct(&fractal(PerpendicularBurningShip::new(), (-1.862, -0.0009), 1e-3, 1000), min);
+    }
+
+    #[test]
+    fn diffabs_in_all_quadrants() {
+        // (c, c + d)の符号の4通り
+        let cases: [(Float, Float); 7] = [(3.0, 1.0), (3.0, -5.0), (-3.0, 5.0), (-3.0, 1.0), (0.0, 2.0), (0.0, -2.0), (2.0, -2.0)];
+        for (c, d) in cases {
+            let expected = (c + d).abs() - c.abs();
+            assert_eq!(diffabs(c, d), expected, "diffabs({}, {})", c, d);
+        }
+        // |c| >> |d|でも桁落ちしない
+        assert_eq!(diffabs(1e20, 1e-5), 1e-5);
+        assert_eq!(diffabs(-1e20, 1e-5), -1e-5);
+        assert_eq!(diffabs(1e20, -1e-5), -1e-5);
+    }
+}
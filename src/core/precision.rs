@@ -0,0 +1,109 @@
+use std::fmt;
+
+use crate::util::big_float::BigFloat;
+use crate::util::types::Float;
+
+// 描画の中心座標を保持する型．Precisionの最大精度に合わせる
+pub type ViewFloat = BigFloat<16>;
+
+// 1pixelの大きさより細かく区別するために余分に持つbit数
+const GUARD_BITS: i64 = 16;
+
+// 反復に使う実数型．ImageConfig::scaleから自動で選ぶ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    F64,  // f64
+    DoubleDouble,  // DoubleDouble
+    Big128,  // BigFloat<2>
+    Big256,  // BigFloat<4>
+    Big512,  // BigFloat<8>
+    Big1024,  // BigFloat<16>
+}
+
+impl Precision {
+    // 1pixelの大きさがscaleのときに足りる最も低い精度
+    // scale = 0(描画範囲が0)は最大精度，負やinf, NaNのscaleはF64になる
+    pub fn from_scale(scale: Float) -> Self {
+        let bits = ((-scale.log2()).ceil() as i64).saturating_add(GUARD_BITS);
+        match bits {
+            ..=53 => Precision::F64,
+            54..=106 => Precision::DoubleDouble,
+            107..=128 => Precision::Big128,
+            129..=256 => Precision::Big256,
+            257..=512 => Precision::Big512,
+            _ => Precision::Big1024,
+        }
+    }
+
+    // 最も高い精度でも1pixelを区別できる最小のscale
+    pub fn min_scale() -> Float {
+        (2.0 as Float).powi(-(Precision::Big1024.bits() as i32 - GUARD_BITS as i32))
+    }
+
+    // 仮数部のbit数
+    pub fn bits(&self) -> u32 {
+        match self {
+            Precision::F64 => 53,
+            Precision::DoubleDouble => 106,
+            Precision::Big128 => 128,
+            Precision::Big256 => 256,
+            Precision::Big512 => 512,
+            Precision::Big1024 => 1024,
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::F64 => write!(f, "f64"),
+            Precision::DoubleDouble => write!(f, "double-double"),
+            _ => write!(f, "BigFloat {}bit", self.bits()),
+        }
+    }
+}
+
+
+// 実際の描画方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Direct(Precision),  // 全pixelをその精度で反復する
+    Perturbation(Precision),  // 参照軌道だけをその精度で求め，各pixelはf64のずれを反復する
+}
+
+impl Backend {
+    pub fn precision(&self) -> Precision {
+        match self {
+            Backend::Direct(p) | Backend::Perturbation(p) => *p,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Direct(p) => write!(f, "{}", p),
+            Backend::Perturbation(p) => write!(f, "perturbation (reference: {})", p),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precision_from_scale() {
+        assert_eq!(Precision::from_scale(3.0 / 1024.0), Precision::F64);
+        assert_eq!(Precision::from_scale(1e-20), Precision::DoubleDouble);
+        assert_eq!(Precision::from_scale(Precision::min_scale()), Precision::Big1024);
+
+        // 範囲外のscaleでもpanicしない
+        assert_eq!(Precision::from_scale(0.0), Precision::Big1024);
+        assert_eq!(Precision::from_scale(1e-320), Precision::Big1024);
+        assert_eq!(Precision::from_scale(Float::INFINITY), Precision::F64);
+        assert_eq!(Precision::from_scale(-1.0), Precision::F64);
+        assert_eq!(Precision::from_scale(Float::NAN), Precision::F64);
+    }
+}
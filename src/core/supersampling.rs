@@ -0,0 +1,114 @@
+use crate::util::types::Float;
+
+// 1pixel内の標本点の取り方．nは1辺あたりの標本数で，1pixelあたりn * n点を使う
+// pixel (x, y)はpixel座標(x, y)の点で代表し，標本点はその点を中心とする1pixel四方に取る
+// どの方法でも標本の中心は同じ点なので，Noneや1点目の描画と位置がずれない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Supersampling {
+    #[default]
+    None,  // pixelを代表する1点だけ
+    Grid(usize),  // 等間隔の格子
+    RotatedGrid(usize),  // 格子をatan(1/n)回転させ，縦横どちらにも標本が重ならないようにしたもの
+    Jittered(usize),  // 格子の各マスの中でランダムに1点ずつ
+    // まず1pixel 1点で描き，隣のpixelと色の成分がthresholdより大きく違うか，脱出したかや反復回数など
+    // evaluatorの値が違うpixelだけRotatedGrid(n)で描き直す
+    Adaptive { n: usize, threshold: u8 },
+}
+
+impl Supersampling {
+    // 1pixelあたりの標本数．Adaptiveでは描き直すpixelでの数
+    pub fn samples(&self) -> usize {
+        match *self {
+            Supersampling::None => 1,
+            Supersampling::Grid(n)
+            | Supersampling::RotatedGrid(n)
+            | Supersampling::Jittered(n)
+            | Supersampling::Adaptive { n, .. } => n.max(1) * n.max(1),
+        }
+    }
+
+    // pixel内の標本点を，pixelを代表する点からのずれ([-0.5, 0.5) x [-0.5, 0.5))で返す
+    // Jitteredの乱数はpixelの位置から決まるので，同じ画像は何度描いても同じになる
+    pub fn offsets(&self, pixel: (usize, usize)) -> Vec<(Float, Float)> {
+        (0..self.samples()).map(|k| self.offset(pixel, k)).collect()
+    }
+
+    // k番目(k < samples())の標本点．描画時にVecを作らずに標本点を回すのに使う
+    pub fn offset(&self, pixel: (usize, usize), k: usize) -> (Float, Float) {
+        let n = match *self {
+            Supersampling::None => return (0.0, 0.0),
+            Supersampling::Adaptive { n, .. } => return Supersampling::RotatedGrid(n).offset(pixel, k),
+            Supersampling::Grid(n)
+            | Supersampling::RotatedGrid(n)
+            | Supersampling::Jittered(n) => n.max(1),
+        };
+        let step = 1.0 / n as Float;
+        let (i, j) = (k % n, k / n);
+
+        match *self {
+            Supersampling::RotatedGrid(_) => {
+                // 格子点(u, v)を回転して(1 + 1/n^2)^(1/2)倍する
+                let u = (i as Float + 0.5) * step - 0.5;
+                let v = (j as Float + 0.5) * step - 0.5;
+                (u - v * step, v + u * step)
+            }
+            Supersampling::Jittered(_) => {
+                let h = hash(pixel.0 as u64, pixel.1 as u64, k as u64);
+                let (rx, ry) = (unit(h), unit(h >> 32));
+                ((i as Float + rx) * step - 0.5, (j as Float + ry) * step - 0.5)
+            }
+            _ => ((i as Float + 0.5) * step - 0.5, (j as Float + 0.5) * step - 0.5),
+        }
+    }
+}
+
+// (x, y, k)から64bitの疑似乱数を作る(splitmix64の混合関数)
+fn hash(x: u64, y: u64, k: u64) -> u64 {
+    let mut h = x
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ y.wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ k.wrapping_mul(0x1656_67b1_9e37_79f9);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+// 下位32bitを[0, 1)の実数にする
+fn unit(h: u64) -> Float {
+    (h & 0xffff_ffff) as Float / (1u64 << 32) as Float
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [Supersampling; 6] = [
+        Supersampling::None,
+        Supersampling::Grid(1),
+        Supersampling::Grid(3),
+        Supersampling::RotatedGrid(4),
+        Supersampling::Jittered(4),
+        Supersampling::Adaptive { n: 3, threshold: 8 },
+    ];
+
+    #[test]
+    fn offsets_are_centred_on_the_same_point() {
+        for mode in MODES {
+            let offsets = mode.offsets((5, 7));
+            assert_eq!(offsets.len(), mode.samples());
+            for &(dx, dy) in &offsets {
+                assert!((-0.5..0.5).contains(&dx) && (-0.5..0.5).contains(&dy), "{:?}", mode);
+            }
+            // Jittered以外は標本の重心がちょうど代表点になる
+            if !matches!(mode, Supersampling::Jittered(_)) {
+                let n = offsets.len() as Float;
+                let mean_x = offsets.iter().map(|o| o.0).sum::<Float>() / n;
+                let mean_y = offsets.iter().map(|o| o.1).sum::<Float>() / n;
+                assert!(mean_x.abs() < 1e-12 && mean_y.abs() < 1e-12, "{:?}", mode);
+            }
+        }
+        assert_eq!(Supersampling::None.offsets((0, 0)), vec![(0.0, 0.0)]);
+        assert_eq!(Supersampling::Grid(1).offsets((0, 0)), vec![(0.0, 0.0)]);
+    }
+}
@@ -1,25 +1,40 @@
 
 pub use crate::{
     util::{
+        big_float::BigFloat,
         color::Color,
+        double_double::DoubleDouble,
         palette::Palette,
+        polynomial::Polynomial,
+        quad_double::QuadDouble,
+        real::{Real, ParseRealError, complex_from_f64, complex_to_f64, complex_exp, complex_ln, complex_sin, complex_cos, complex_powf},
         types::*,
     },
 
     core::{
-        complex_dynamics::ComplexDynamics,
+        complex_dynamics::{ComplexDynamics, ConvergentDynamics, DifferentiableDynamics, StatefulDynamics},
         complex_dynamics_presets::*,
 
+        bailout::Bailout,
+        formula::{Formula, FormulaParseError},
+
         escape_evaluator::EscapeEvaluator,
         escape_evaluator_presets::*,
 
         coloring::Coloring,
         coloring_presets::*,
 
-        escape_time_fractal::EscapeTimeFractal,
+        escape_time_fractal::{EscapeTimeFractal, Subdivision},
+        supersampling::Supersampling,
+        precision::{Backend, Precision, ViewFloat},
+        perturbation::{PerturbableDynamics, PerturbationFractal, diffabs},
+        bla::{BlaStep, BlaTable, Mat2},
     },
 
     app::{
         app::App,
     }
-};
\ No newline at end of file
+};
+
+#[cfg(feature = "jit")]
+pub use crate::core::formula::jit::{FormulaJitError, JitFormula};
@@ -1,3 +1,8 @@
+pub mod big_float;
 pub mod color;
+pub mod double_double;
 pub mod palette;
-pub mod types;
\ No newline at end of file
+pub mod polynomial;
+pub mod quad_double;
+pub mod real;
+pub mod types;
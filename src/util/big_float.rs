@@ -0,0 +1,622 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num::traits::{Num, One, Zero};
+use crate::util::real::{Real, ParseRealError, parse_decimal};
+use crate::util::types::Float;
+
+const LIMB_BITS: i64 = 64;
+
+// 無限大とNaNの指数部．有限の値の指数がここまで大きくなることはない
+const NON_FINITE_EXP: i64 = i64::MAX;
+const INFINITY_MANT: u64 = 1 << 63;
+const NAN_MANT: u64 = 3 << 62;
+
+// x * 2^eを途中でオーバーフローさせずに計算する
+fn ldexp(x: Float, e: i64) -> Float {
+    let mut x = x;
+    let mut e = e.clamp(-2200, 2200) as i32;
+    while e > 1000 {
+        x *= Float::powi(2.0, 1000);
+        e -= 1000;
+    }
+    while e < -1000 {
+        x *= Float::powi(2.0, -1000);
+        e += 1000;
+    }
+    x * Float::powi(2.0, e)
+}
+
+/* ===== 仮数部(上位limbから並んだ固定長の小数)の演算 ===== */
+
+fn shr<const N: usize>(m: &[u64; N], bits: i64) -> [u64; N] {
+    let mut out = [0; N];
+    let limbs = (bits / LIMB_BITS) as usize;
+    let rem = (bits % LIMB_BITS) as u32;
+    for (src, limb) in out.iter_mut().skip(limbs).enumerate() {
+        *limb = if rem == 0 { m[src] } else { m[src] >> rem };
+        if rem != 0 && src > 0 {
+            *limb |= m[src - 1] << (64 - rem);
+        }
+    }
+    out
+}
+
+fn shl<const N: usize>(m: &[u64; N], bits: i64) -> [u64; N] {
+    let mut out = [0; N];
+    let limbs = (bits / LIMB_BITS) as usize;
+    let rem = (bits % LIMB_BITS) as u32;
+    for (i, limb) in out.iter_mut().take(N.saturating_sub(limbs)).enumerate() {
+        let src = i + limbs;
+        *limb = if rem == 0 { m[src] } else { m[src] << rem };
+        if rem != 0 && src + 1 < N {
+            *limb |= m[src + 1] >> (64 - rem);
+        }
+    }
+    out
+}
+
+// 繰り上がりがあればtrue
+fn add_mag<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], bool) {
+    let mut out = [0; N];
+    let mut carry = false;
+    for i in (0..N).rev() {
+        let (s, c1) = a[i].overflowing_add(b[i]);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        out[i] = s;
+        carry = c1 || c2;
+    }
+    (out, carry)
+}
+
+// a >= bを仮定する．繰り下がりがあればtrue
+fn sub_mag<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], bool) {
+    let mut out = [0; N];
+    let mut borrow = false;
+    for i in (0..N).rev() {
+        let (s, b1) = a[i].overflowing_sub(b[i]);
+        let (s, b2) = s.overflowing_sub(borrow as u64);
+        out[i] = s;
+        borrow = b1 || b2;
+    }
+    (out, borrow)
+}
+
+fn leading_zeros<const N: usize>(m: &[u64; N]) -> i64 {
+    let mut n = 0;
+    for &limb in m {
+        if limb != 0 {
+            return n + limb.leading_zeros() as i64;
+        }
+        n += LIMB_BITS;
+    }
+    n
+}
+
+
+// 仮数部がN個の64bit limbからなる2進浮動小数点数．値は(-1)^negative × 0.mant × 2^exp
+// Copyにするため桁数は型で固定する．丸めは切り捨て
+// 0除算などの結果はf64と同じく±無限大とNaNで表す(exp = NON_FINITE_EXP)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BigFloat<const N: usize> {
+    negative: bool,
+    exp: i64,
+    mant: [u64; N],  // 上位limbから．0以外では最上位bitが1
+}
+
+impl<const N: usize> BigFloat<N> {
+    pub const BITS: u32 = 64 * N as u32;
+
+    pub fn infinity(negative: bool) -> Self {
+        let mut mant = [0; N];
+        mant[0] = INFINITY_MANT;
+        Self { negative, exp: NON_FINITE_EXP, mant }
+    }
+
+    pub fn nan() -> Self {
+        let mut mant = [0; N];
+        mant[0] = NAN_MANT;
+        Self { negative: false, exp: NON_FINITE_EXP, mant }
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        self.exp == NON_FINITE_EXP && self.mant[0] == INFINITY_MANT
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.exp == NON_FINITE_EXP && self.mant[0] != INFINITY_MANT
+    }
+
+    fn normalize(negative: bool, exp: i64, mant: [u64; N]) -> Self {
+        let lz = leading_zeros(&mant);
+        if lz == LIMB_BITS * N as i64 {
+            return Self::zero();
+        }
+        Self { negative, exp: exp - lz, mant: shl(&mant, lz) }
+    }
+
+    /* ===== conversion ===== */
+
+    // 精度の異なる型へ変換する．f64で表せる部分を上から順に取り出して足す
+    pub fn to_real<T: Real>(&self) -> T {
+        if !self.is_finite() {
+            return T::from_f64(self.to_f64());
+        }
+        let mut rest = *self;
+        let mut out = T::zero();
+        for _ in 0..=(Self::BITS / 53 + 1) {
+            let part = rest.to_f64();
+            if part == 0.0 {
+                break;
+            }
+            out = out + T::from_f64(part);
+            rest = rest - Self::from_f64(part);
+        }
+        out
+    }
+
+    /* ===== rounding ===== */
+
+    // 0方向への丸め
+    pub fn trunc(self) -> Self {
+        if self.exp <= 0 {
+            return Self::zero();
+        }
+        if self.exp >= Self::BITS as i64 {
+            return self;
+        }
+        let frac_bits = Self::BITS as i64 - self.exp;
+        let mant = shl(&shr(&self.mant, frac_bits), frac_bits);
+        Self::normalize(self.negative, self.exp, mant)
+    }
+
+    pub fn powi(self, n: u32) -> Self {
+        let mut base = self;
+        let mut n = n;
+        let mut out = Self::one();
+        while n > 0 {
+            if n & 1 == 1 {
+                out = out * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        out
+    }
+
+    fn cmp_abs(&self, other: &Self) -> Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => self.exp.cmp(&other.exp).then_with(|| self.mant.cmp(&other.mant)),
+        }
+    }
+}
+
+impl<const N: usize> Default for BigFloat<N> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const N: usize> PartialOrd for BigFloat<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.is_nan() || other.is_nan() {
+            return None;
+        }
+        let ord = match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_abs(other),
+            (true, true) => other.cmp_abs(self),
+        };
+        Some(ord)
+    }
+}
+
+impl<const N: usize> Add for BigFloat<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        if self.is_nan() || rhs.is_nan() {
+            return Self::nan();
+        }
+        if self.is_infinite() || rhs.is_infinite() {
+            // 異符号の無限大の和だけNaN
+            return match (self.is_infinite(), rhs.is_infinite()) {
+                (true, true) if self.negative != rhs.negative => Self::nan(),
+                (true, _) => self,
+                _ => rhs,
+            };
+        }
+        if self.is_zero() {
+            return rhs;
+        }
+        if rhs.is_zero() {
+            return self;
+        }
+
+        let (a, b) = if self.exp >= rhs.exp { (self, rhs) } else { (rhs, self) };
+        let shift = a.exp - b.exp;
+        if shift >= Self::BITS as i64 {
+            return a;
+        }
+        let b_mant = shr(&b.mant, shift);
+
+        if a.negative == b.negative {
+            let (mant, carry) = add_mag(&a.mant, &b_mant);
+            if !carry {
+                return Self { negative: a.negative, exp: a.exp, mant };
+            }
+            let mut mant = shr(&mant, 1);
+            mant[0] |= 1 << 63;
+            return Self { negative: a.negative, exp: a.exp + 1, mant };
+        }
+
+        // 異符号なら絶対値の大きい方から小さい方を引く
+        if a.mant >= b_mant {
+            let (mant, _) = sub_mag(&a.mant, &b_mant);
+            Self::normalize(a.negative, a.exp, mant)
+        } else {
+            let (mant, _) = sub_mag(&b_mant, &a.mant);
+            Self::normalize(b.negative, a.exp, mant)
+        }
+    }
+}
+
+impl<const N: usize> Sub for BigFloat<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+// 上位N + 1 limbだけを列ごとに累算する
+impl<const N: usize> Mul for BigFloat<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        if self.is_nan() || rhs.is_nan() {
+            return Self::nan();
+        }
+        if self.is_infinite() || rhs.is_infinite() {
+            if self.is_zero() || rhs.is_zero() {
+                return Self::nan();
+            }
+            return Self::infinity(self.negative != rhs.negative);
+        }
+        if self.is_zero() || rhs.is_zero() {
+            return Self::zero();
+        }
+
+        // a[i] * b[j]の上位64bitはi + j列目，下位64bitはi + j + 1列目に入る．N列目は丸め用
+        let mut mant = [0; N];
+        let mut guard = 0;
+        let mut carry: u128 = 0;
+        for k in (0..=N).rev() {
+            let mut acc = carry;
+            for i in 0..N.min(k + 1) {
+                let j = k - i;
+                if j < N {
+                    acc += (self.mant[i] as u128 * rhs.mant[j] as u128) >> 64;
+                }
+                if j >= 1 && j - 1 < N {
+                    acc += (self.mant[i] as u128 * rhs.mant[j - 1] as u128) as u64 as u128;
+                }
+            }
+            if k == N {
+                guard = acc as u64;
+            } else {
+                mant[k] = acc as u64;
+            }
+            carry = acc >> 64;
+        }
+
+        let negative = self.negative != rhs.negative;
+        let exp = self.exp + rhs.exp;
+        // 積は[0.25, 1)なので高々1bitずらせばよい
+        if mant[0] >> 63 == 1 {
+            return Self { negative, exp, mant };
+        }
+        let mut mant = shl(&mant, 1);
+        mant[N - 1] |= guard >> 63;
+        Self { negative, exp: exp - 1, mant }
+    }
+}
+
+// 2進の筆算で商をN * 64 + 1bit求める
+// 0除算はf64と同じく，0 / 0ならNaN，それ以外は符号付きの無限大になる(0は常に正として扱う)
+impl<const N: usize> Div for BigFloat<N> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        if self.is_nan() || rhs.is_nan() {
+            return Self::nan();
+        }
+        let negative = self.negative != rhs.negative;
+        match (self.is_infinite(), rhs.is_infinite()) {
+            (true, true) => return Self::nan(),
+            (true, false) => return Self::infinity(negative),
+            (false, true) => return Self::zero(),
+            _ => {}
+        }
+        if rhs.is_zero() {
+            return if self.is_zero() { Self::nan() } else { Self::infinity(self.negative) };
+        }
+        if self.is_zero() {
+            return Self::zero();
+        }
+
+        let mut top = 0u64;  // 剰余の最上位に溢れたbit
+        let mut rem = self.mant;
+        let mut int_bit = false;
+        let mut quot = [0u64; N];
+        for k in 0..=Self::BITS as usize {
+            if top > 0 || rem >= rhs.mant {
+                let (r, borrow) = sub_mag(&rem, &rhs.mant);
+                rem = r;
+                top -= borrow as u64;
+                if k == 0 {
+                    int_bit = true;
+                } else {
+                    quot[(k - 1) / 64] |= 1 << (63 - (k - 1) % 64);
+                }
+            }
+            top = (top << 1) | (rem[0] >> 63);
+            rem = shl(&rem, 1);
+        }
+
+        let exp = self.exp - rhs.exp;
+        if !int_bit {
+            return Self { negative, exp, mant: quot };
+        }
+        let mut mant = shr(&quot, 1);
+        mant[0] |= 1 << 63;
+        Self { negative, exp: exp + 1, mant }
+    }
+}
+
+impl<const N: usize> Rem for BigFloat<N> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        self - rhs * (self / rhs).trunc()
+    }
+}
+
+impl<const N: usize> Neg for BigFloat<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        if self.is_zero() {
+            return self;
+        }
+        Self { negative: !self.negative, ..self }
+    }
+}
+
+impl<const N: usize> Zero for BigFloat<N> {
+    fn zero() -> Self {
+        Self { negative: false, exp: 0, mant: [0; N] }
+    }
+    fn is_zero(&self) -> bool {
+        self.mant[0] == 0
+    }
+}
+
+impl<const N: usize> One for BigFloat<N> {
+    fn one() -> Self {
+        Self::from_f64(1.0)
+    }
+}
+
+impl<const N: usize> Num for BigFloat<N> {
+    type FromStrRadixErr = ParseRealError;
+
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseRealError> {
+        parse_decimal(src, radix)
+    }
+}
+
+impl<const N: usize> Real for BigFloat<N> {
+    // -0.0は0として扱う
+    fn from_f64(x: Float) -> Self {
+        if x.is_nan() {
+            return Self::nan();
+        }
+        if x.is_infinite() {
+            return Self::infinity(x < 0.0);
+        }
+        if x == 0.0 {
+            return Self::zero();
+        }
+        let bits = x.abs().to_bits();
+        let biased = ((bits >> 52) & 0x7ff) as i64;
+        let frac = bits & ((1 << 52) - 1);
+        // x = m × 2^e
+        let (m, e) = if biased == 0 { (frac, -1074) } else { (frac | (1 << 52), biased - 1075) };
+        let lz = m.leading_zeros() as i64;
+        let mut mant = [0; N];
+        mant[0] = m << lz;
+        Self { negative: x < 0.0, exp: e + LIMB_BITS - lz, mant }
+    }
+    fn to_f64(self) -> Float {
+        if self.is_nan() {
+            return Float::NAN;
+        }
+        // 無限大はldexpでf64の範囲を超えて±infになる
+        let x = ldexp(self.mant[0] as Float, self.exp - LIMB_BITS);
+        if self.negative { -x } else { x }
+    }
+    fn abs(self) -> Self {
+        Self { negative: false, ..self }
+    }
+    fn signum(self) -> Self {
+        if self.negative { -Self::one() } else { Self::one() }
+    }
+    // 指数部はi64なので，無限大になるのは0除算などの場合だけ
+    fn is_finite(self) -> bool {
+        self.exp != NON_FINITE_EXP
+    }
+}
+
+// 10進の指数表記．精度指定は小数点以下の桁数で，省略時は型の精度いっぱいまで出す
+impl<const N: usize> fmt::Display for BigFloat<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        if self.is_nan() {
+            return write!(f, "NaN");
+        }
+        if self.is_infinite() {
+            return write!(f, "{}inf", if self.negative { "-" } else { "" });
+        }
+
+        let ten = Self::from_f64(10.0);
+        let x = self.abs();
+
+        // 10^d <= x < 10^(d + 1)となるdを求め，xを[1, 10)に寄せる
+        let log10 = (self.mant[0] as Float).log10()
+            + (self.exp - LIMB_BITS) as Float * std::f64::consts::LOG10_2;
+        let mut d = log10.floor() as i64;
+        let scale = ten.powi(d.unsigned_abs() as u32);
+        let mut y = if d >= 0 { x / scale } else { x * scale };
+        if y >= ten {
+            y = y / ten;
+            d += 1;
+        }
+        if y < Self::one() {
+            y = y * ten;
+            d -= 1;
+        }
+
+        let max_digits = (Self::BITS as Float * std::f64::consts::LOG10_2) as usize - 1;
+        let digits = f.precision().map_or(max_digits, |p| p.min(max_digits));
+        let mut out = String::new();
+        if self.negative {
+            out.push('-');
+        }
+        for i in 0..=digits {
+            let digit = if y.exp > 0 { y.mant[0] >> (64 - y.exp) } else { 0 };
+            out.push((b'0' + digit as u8) as char);
+            if i == 0 && digits > 0 {
+                out.push('.');
+            }
+            y = (y - Self::from_f64(digit as Float)) * ten;
+        }
+        if f.precision().is_none() && out.contains('.') {
+            let trimmed = out.trim_end_matches('0').trim_end_matches('.').len();
+            out.truncate(trimmed);
+        }
+        write!(f, "{}e{}", out, d)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type B = BigFloat<2>;
+
+    #[test]
+    fn division_by_zero_gives_infinity_or_nan() {
+        let (one, zero) = (B::one(), B::zero());
+        assert!((one / zero).is_infinite());
+        assert_eq!((one / zero).to_f64(), Float::INFINITY);
+        assert_eq!((-one / zero).to_f64(), Float::NEG_INFINITY);
+        assert!((zero / zero).is_nan());
+        assert!((zero / zero).to_f64().is_nan());
+        assert!((one % zero).is_nan());
+
+        // 無限大との演算もf64に合わせる
+        let inf = one / zero;
+        assert_eq!(one / inf, zero);
+        assert_eq!((inf + one).to_f64(), Float::INFINITY);
+        assert!((inf - inf).is_nan());
+        assert!((inf * zero).is_nan());
+        assert_eq!((inf * -one).to_f64(), Float::NEG_INFINITY);
+        assert!(!inf.is_finite() && one.is_finite());
+        assert!(inf > B::from_f64(1e300) && -inf < B::from_f64(-1e300));
+        assert_eq!((zero / zero).partial_cmp(&one), None);
+        assert_eq!(format!("{}", -inf), "-inf");
+        assert_eq!(inf.to_real::<Float>(), Float::INFINITY);
+    }
+
+    #[test]
+    fn zero_has_no_sign() {
+        let zero = B::zero();
+        assert_eq!(-zero, zero);
+        assert_eq!(B::from_f64(-0.0), zero);
+        let x = B::from_f64(-1.25);
+        assert_eq!(x - x, zero);
+        assert_eq!(x + -x, zero);
+        assert_eq!(x * zero, zero);
+        assert_eq!(zero / x, zero);
+        assert_eq!((x - x).to_f64().to_bits(), 0.0f64.to_bits());
+        assert_eq!(zero.partial_cmp(&-zero), Some(Ordering::Equal));
+    }
+
+    // 2^-n(正確に表せる)
+    fn pow2_neg<const N: usize>(n: u32) -> BigFloat<N> {
+        BigFloat::from_f64(0.5).powi(n)
+    }
+
+    fn check_arithmetic<const N: usize>() {
+        let one = BigFloat::<N>::one();
+        let tiny = pow2_neg::<N>(200);
+
+        // 桁落ちしても2^-200が正確に残る
+        assert_eq!(one + tiny - one, tiny);
+        assert_eq!((one + tiny) - (one + pow2_neg(201)), pow2_neg(201));
+        assert_eq!(-(one - tiny) + one, tiny);
+
+        // (1 + 2^-100)^2 = 1 + 2^-99 + 2^-200は201bitに収まるので正確
+        let x = one + pow2_neg(100);
+        let square = one + pow2_neg(99) + tiny;
+        assert_eq!(x * x, square);
+        assert_eq!(square / x, x);
+
+        // 丸めは切り捨てなので誤差は最下位bit程度
+        let eps = pow2_neg::<N>(BigFloat::<N>::BITS - 2);
+        for d in [3.0, 7.0, 10.0] {
+            let d = BigFloat::<N>::from_f64(d);
+            let error = (one / d * d - one).abs();
+            assert!(error <= eps, "N = {}, d = {}: {}", N, d, error);
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_exact_up_to_the_last_bit() {
+        check_arithmetic::<4>();
+        check_arithmetic::<16>();
+
+        // 128bitでは2^-200は1に吸収される
+        let one = B::one();
+        assert_eq!(one + pow2_neg(200) - one, B::zero());
+    }
+
+    #[test]
+    fn f64_round_trip() {
+        for x in [
+            1.0, -1.0, 0.1, -2.5e-8, 3.0e300, -1.7976931348623157e308, 2.2250738585072014e-308,
+            5e-324, -1.5e-310, std::f64::consts::PI, Float::INFINITY, Float::NEG_INFINITY,
+        ] {
+            assert_eq!(B::from_f64(x).to_f64(), x, "{}", x);
+            assert_eq!(BigFloat::<16>::from_f64(x).to_f64(), x, "{}", x);
+        }
+        assert!(B::from_f64(Float::NAN).to_f64().is_nan());
+
+        // f64の範囲を超える値は±inf，下回る値は0になる
+        let big = B::from_f64(1e300) * B::from_f64(1e300);
+        assert!(big.is_finite());
+        assert_eq!(big.to_f64(), Float::INFINITY);
+        let small = B::from_f64(1e-300) * B::from_f64(1e-300);
+        assert_eq!(small.to_f64(), 0.0);
+        assert!(((small / B::from_f64(1e-300)).to_f64() / 1e-300 - 1.0).abs() < 1e-12);
+    }
+}
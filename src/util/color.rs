@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color([u8; 4]);
 
 pub enum ColorParseError {
@@ -143,6 +143,51 @@ impl Color {
         self.0[Self::B] = corr(self.get_b(), inv);
     }
 
+    /* ===== interpolation ===== */
+
+    // selfとotherをt(0.0..=1.0)で線形補間する．t = 0でself, t = 1でother
+    pub fn lerp(&self, other: &Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mut out = *self;
+        for i in 0..4 {
+            let a = self.0[i] as f32;
+            let b = other.0[i] as f32;
+            out.0[i] = (a + (b - a) * t).round() as u8;
+        }
+        out
+    }
+
+    // 成分ごとの差の絶対値の最大
+    pub fn max_diff(&self, other: &Color) -> u8 {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // 各成分の平均．空ならBLACK
+    pub fn mean(colors: &[Color]) -> Self {
+        Color::mean_of(colors.iter().copied())
+    }
+
+    // meanと同じだが，標本ごとに色を作りながら平均するときにVecを作らずに済む
+    pub fn mean_of(colors: impl IntoIterator<Item = Color>) -> Self {
+        let mut sum = [0u32; 4];
+        let mut n = 0u32;
+        for c in colors {
+            for (s, &v) in sum.iter_mut().zip(&c.0) {
+                *s += v as u32;
+            }
+            n += 1;
+        }
+        if n == 0 {
+            return Color::BLACK;
+        }
+        Color(sum.map(|s| ((s + n / 2) / n) as u8))
+    }
+
     /* ===== raw access ===== */
 
     pub fn as_rgba(&self) -> &[u8; 4] {
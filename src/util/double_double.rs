@@ -0,0 +1,215 @@
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num::traits::{Num, One, Zero};
+use crate::util::real::{Real, ParseRealError, parse_decimal};
+use crate::util::types::Float;
+
+/* ===== error-free transformations ===== */
+
+// a + b = s + eの(s, e)を返す
+#[inline]
+pub(crate) fn two_sum(a: Float, b: Float) -> (Float, Float) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+// |a| >= |b|のときのtwo_sum
+#[inline]
+pub(crate) fn quick_two_sum(a: Float, b: Float) -> (Float, Float) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+// a * b = p + eの(p, e)を返す
+#[inline]
+pub(crate) fn two_prod(a: Float, b: Float) -> (Float, Float) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+
+// double-double: 値はhi + lo(|lo| <= ulp(hi) / 2)で，約106bitの精度を持つ
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    hi: Float,
+    lo: Float,
+}
+
+impl DoubleDouble {
+    /* ===== constructors ===== */
+
+    pub fn new(hi: Float, lo: Float) -> Self {
+        let (hi, lo) = two_sum(hi, lo);
+        Self { hi, lo }
+    }
+
+    /* ===== getters ===== */
+
+    pub fn hi(&self) -> Float {
+        self.hi
+    }
+
+    pub fn lo(&self) -> Float {
+        self.lo
+    }
+
+    /* ===== rounding ===== */
+
+    pub fn floor(self) -> Self {
+        let hi = self.hi.floor();
+        if hi != self.hi {
+            return Self { hi, lo: 0.0 };
+        }
+        // hiが整数ならloの端数を切り捨てる
+        let (hi, lo) = quick_two_sum(hi, self.lo.floor());
+        Self { hi, lo }
+    }
+
+    pub fn trunc(self) -> Self {
+        if self.hi >= 0.0 { self.floor() } else { -(-self).floor() }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (s1, s2) = two_sum(self.hi, rhs.hi);
+        let (t1, t2) = two_sum(self.lo, rhs.lo);
+        let (s1, s2) = quick_two_sum(s1, s2 + t1);
+        let (hi, lo) = quick_two_sum(s1, s2 + t2);
+        Self { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (p1, p2) = two_prod(self.hi, rhs.hi);
+        let p2 = p2 + (self.hi * rhs.lo + self.lo * rhs.hi);
+        let (hi, lo) = quick_two_sum(p1, p2);
+        Self { hi, lo }
+    }
+}
+
+// 商をf64で3回求めて余りを補正する
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let q1 = self.hi / rhs.hi;
+        let r = self - rhs * Self::from_f64(q1);
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * Self::from_f64(q2);
+        let q3 = r.hi / rhs.hi;
+        let (hi, lo) = quick_two_sum(q1, q2);
+        Self { hi, lo } + Self::from_f64(q3)
+    }
+}
+
+impl Rem for DoubleDouble {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        self - rhs * (self / rhs).trunc()
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl Zero for DoubleDouble {
+    fn zero() -> Self {
+        Self { hi: 0.0, lo: 0.0 }
+    }
+    fn is_zero(&self) -> bool {
+        self.hi == 0.0
+    }
+}
+
+impl One for DoubleDouble {
+    fn one() -> Self {
+        Self { hi: 1.0, lo: 0.0 }
+    }
+}
+
+impl Num for DoubleDouble {
+    type FromStrRadixErr = ParseRealError;
+
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseRealError> {
+        parse_decimal(src, radix)
+    }
+}
+
+impl Real for DoubleDouble {
+    fn from_f64(x: Float) -> Self {
+        Self { hi: x, lo: 0.0 }
+    }
+    fn to_f64(self) -> Float {
+        self.hi + self.lo
+    }
+    fn abs(self) -> Self {
+        if self.hi < 0.0 { -self } else { self }
+    }
+    fn signum(self) -> Self {
+        Self::from_f64(self.hi.signum())
+    }
+    fn is_finite(self) -> bool {
+        self.hi.is_finite() && self.lo.is_finite()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn division_is_accurate_to_double_double_precision() {
+        let one = DoubleDouble::one();
+        let three = DoubleDouble::from_f64(3.0);
+        let error = (one / three * three - one).to_f64().abs();
+        assert!(error < 1e-31, "{}", error);
+        // f64ではこの誤差は1e-16程度になる
+        assert_ne!((one / three).lo(), 0.0);
+
+        let x = DoubleDouble::new(1.0, 1e-20);
+        let y = DoubleDouble::from_f64(7.0);
+        assert!((x / y * y - x).to_f64().abs() < 1e-31);
+    }
+
+    #[test]
+    fn floor_and_trunc_of_negative_numbers() {
+        let dd = DoubleDouble::from_f64;
+        assert_eq!(dd(-1.5).floor(), dd(-2.0));
+        assert_eq!(dd(-1.5).trunc(), dd(-1.0));
+        assert_eq!(dd(-2.0).floor(), dd(-2.0));
+        assert_eq!(dd(-2.0).trunc(), dd(-2.0));
+        assert_eq!(dd(1.5).trunc(), dd(1.0));
+
+        // hiが整数でもloの端数で結果が変わる
+        let above = DoubleDouble::new(-2.0, 1e-20);
+        assert_eq!(above.floor(), dd(-2.0));
+        assert_eq!(above.trunc(), dd(-1.0));
+        let below = DoubleDouble::new(-2.0, -1e-20);
+        assert_eq!(below.floor(), dd(-3.0));
+        assert_eq!(below.trunc(), dd(-2.0));
+
+        assert_eq!(dd(-7.5) % dd(2.0), dd(-1.5));
+    }
+}
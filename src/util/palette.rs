@@ -1,6 +1,6 @@
 use crate::util::color::Color;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Palette (Vec<Color>);
 
 impl Palette {
@@ -0,0 +1,149 @@
+use num::traits::Zero;
+use num_complex::{self, Complex};
+use crate::util::real::{Real, complex_from_f64};
+use crate::util::types::Float;
+
+// 複素係数多項式．coeffs[i]がz^iの係数
+#[derive(Debug, Clone)]
+pub struct Polynomial(Vec<Complex<Float>>);
+
+impl Polynomial {
+    // 低次の係数から順に並べる．最高次の0係数は取り除く
+    pub fn new(coeffs: Vec<Complex<Float>>) -> Self {
+        let mut coeffs = coeffs;
+        while coeffs.len() > 1 && coeffs.last() == Some(&Complex::ZERO) {
+            coeffs.pop();
+        }
+        if coeffs.is_empty() {
+            coeffs.push(Complex::ZERO);
+        }
+        Self(coeffs)
+    }
+
+    // (z - r0)(z - r1)...を展開する
+    pub fn from_roots(roots: &[Complex<Float>]) -> Self {
+        let mut coeffs = vec![Complex::ONE];
+        for &r in roots {
+            let mut next = vec![Complex::ZERO; coeffs.len() + 1];
+            for (i, &a) in coeffs.iter().enumerate() {
+                next[i + 1] += a;
+                next[i] -= a * r;
+            }
+            coeffs = next;
+        }
+        Self(coeffs)
+    }
+
+    pub fn coeffs(&self) -> &[Complex<Float>] {
+        &self.0
+    }
+
+    pub fn degree(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    // Horner法．係数はzの型に変換して使う
+    pub fn eval<T: Real>(&self, z: Complex<T>) -> Complex<T> {
+        self.0
+            .iter()
+            .rev()
+            .fold(Complex::zero(), |acc, &a| acc * z + complex_from_f64(a))
+    }
+
+    // (p(z), p'(z))を同時に求める
+    pub fn eval_with_derivative<T: Real>(&self, z: Complex<T>) -> (Complex<T>, Complex<T>) {
+        let mut p = Complex::zero();
+        let mut dp = Complex::zero();
+        for &a in self.0.iter().rev() {
+            dp = dp * z + p;
+            p = p * z + complex_from_f64(a);
+        }
+        (p, dp)
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let coeffs = self.0
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &a)| a * i as Float)
+            .collect();
+        Polynomial::new(coeffs)
+    }
+
+    // Durand-Kerner法で全ての根を求める
+    pub fn roots(&self) -> Vec<Complex<Float>> {
+        let n = self.degree();
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let lead = self.0[n];
+        let monic: Vec<Complex<Float>> = self.0.iter().map(|&a| a / lead).collect();
+        let monic = Polynomial(monic);
+
+        // 初期値は単位円上からずらした点の冪
+        let seed = Complex::new(0.4, 0.9);
+        let mut roots: Vec<Complex<Float>> = (0..n).map(|i| seed.powi(i as i32)).collect();
+
+        for _ in 0..500 {
+            let mut max_delta: Float = 0.0;
+            for i in 0..n {
+                let mut denom = Complex::ONE;
+                for j in 0..n {
+                    if i != j {
+                        denom *= roots[i] - roots[j];
+                    }
+                }
+                let delta = monic.eval(roots[i]) / denom;
+                roots[i] -= delta;
+                max_delta = max_delta.max(delta.norm());
+            }
+            if max_delta < 1e-14 {
+                break;
+            }
+        }
+
+        roots
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_minus_one() -> Polynomial {
+        Polynomial::new(vec![Complex::new(-1.0, 0.0), Complex::ZERO, Complex::ZERO, Complex::ONE])
+    }
+
+    #[test]
+    fn roots_of_cube_minus_one_in_stable_order() {
+        let h = (3.0 as Float).sqrt() / 2.0;
+        let expected = [Complex::new(1.0, 0.0), Complex::new(-0.5, -h), Complex::new(-0.5, h)];
+        // 初期値が決まっているので，何度求めても同じ順に並ぶ
+        for _ in 0..3 {
+            let roots = cube_minus_one().roots();
+            assert_eq!(roots.len(), 3);
+            for (r, e) in roots.iter().zip(expected) {
+                assert!((r - e).norm() < 1e-12, "{} != {}", r, e);
+            }
+        }
+    }
+
+    #[test]
+    fn from_roots_and_derivative() {
+        let p = cube_minus_one();
+        let q = Polynomial::from_roots(&p.roots());
+        for (a, b) in q.coeffs().iter().zip(p.coeffs()) {
+            assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+        }
+
+        let z: Complex<Float> = Complex::new(0.3, -1.2);
+        let (value, slope) = p.eval_with_derivative(z);
+        assert!((value - (z * z * z - 1.0)).norm() < 1e-12);
+        assert!((slope - 3.0 * z * z).norm() < 1e-12);
+        assert!((p.derivative().eval(z) - slope).norm() < 1e-12);
+        assert_eq!(p.degree(), 3);
+    }
+}
@@ -0,0 +1,314 @@
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num::traits::{Num, One, Zero};
+use crate::util::double_double::{two_prod, two_sum, quick_two_sum};
+use crate::util::real::{Real, ParseRealError, parse_decimal};
+use crate::util::types::Float;
+
+// a + b + cを(a, b, c)の大きい順に並べ直す
+#[inline]
+fn three_sum(a: Float, b: Float, c: Float) -> (Float, Float, Float) {
+    let (t1, t2) = two_sum(a, b);
+    let (a, t3) = two_sum(c, t1);
+    let (b, c) = two_sum(t2, t3);
+    (a, b, c)
+}
+
+// 加算で使う2項の累算器(u, v)にcを足す．上位に確定した項があれば返す
+#[inline]
+fn quick_three_accum(u: &mut Float, v: &mut Float, c: Float) -> Float {
+    let (s, b) = two_sum(*v, c);
+    let (s, a) = two_sum(*u, s);
+    *u = a;
+    *v = b;
+
+    if a != 0.0 && b != 0.0 {
+        return s;
+    }
+    if b == 0.0 {
+        *v = a;
+    }
+    *u = s;
+    0.0
+}
+
+// 重なりのある5項を，重なりのない4項に直す
+fn renorm(c: [Float; 5]) -> [Float; 4] {
+    let [c0, c1, c2, c3, c4] = c;
+    if c0.is_infinite() {
+        return [c0, c1, c2, c3];
+    }
+
+    let (s, c4) = quick_two_sum(c3, c4);
+    let (s, c3) = quick_two_sum(c2, s);
+    let (s, c2) = quick_two_sum(c1, s);
+    let (c0, c1) = quick_two_sum(c0, s);
+
+    // 0になった項を詰めながら順に足し込む
+    let mut out = [0.0; 4];
+    let mut k = 0;
+    let mut acc = c0;
+    for t in [c1, c2, c3, c4] {
+        // 4項目はそれ以降の項を単に足す
+        if k == 3 {
+            acc += t;
+            continue;
+        }
+        let (s, e) = quick_two_sum(acc, t);
+        if e != 0.0 {
+            out[k] = s;
+            k += 1;
+            acc = e;
+        } else {
+            acc = s;
+        }
+    }
+    out[k] = acc;
+    out
+}
+
+
+// quad-double: 値は4つのf64の和で，約212bitの精度を持つ
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct QuadDouble([Float; 4]);
+
+impl QuadDouble {
+    /* ===== constructors ===== */
+
+    pub fn new(parts: [Float; 4]) -> Self {
+        let [a, b, c, d] = parts;
+        Self(renorm([a, b, c, d, 0.0]))
+    }
+
+    /* ===== getters ===== */
+
+    // 大きい順に並んだ4項
+    pub fn parts(&self) -> [Float; 4] {
+        self.0
+    }
+
+    /* ===== rounding ===== */
+
+    pub fn floor(self) -> Self {
+        let mut x = [0.0; 4];
+        // 上の項が整数のときだけ下の項の端数を切り捨てる
+        for (xi, &a) in x.iter_mut().zip(&self.0) {
+            *xi = a.floor();
+            if *xi != a {
+                break;
+            }
+        }
+        Self(renorm([x[0], x[1], x[2], x[3], 0.0]))
+    }
+
+    pub fn trunc(self) -> Self {
+        if self.0[0] >= 0.0 { self.floor() } else { -(-self).floor() }
+    }
+}
+
+// 2つの4項を絶対値の大きい順に併合しながら足す
+impl Add for QuadDouble {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (a, b) = (self.0, rhs.0);
+        let mut x = [0.0; 4];
+        let (mut i, mut j, mut k) = (0, 0, 0);
+
+        let next = |i: &mut usize, j: &mut usize| {
+            if *i >= 4 {
+                *j += 1;
+                b[*j - 1]
+            } else if *j >= 4 || a[*i].abs() > b[*j].abs() {
+                *i += 1;
+                a[*i - 1]
+            } else {
+                *j += 1;
+                b[*j - 1]
+            }
+        };
+
+        let u = next(&mut i, &mut j);
+        let v = next(&mut i, &mut j);
+        let (mut u, mut v) = quick_two_sum(u, v);
+
+        while k < 4 {
+            if i >= 4 && j >= 4 {
+                x[k] = u;
+                if k < 3 {
+                    x[k + 1] = v;
+                }
+                break;
+            }
+
+            let t = next(&mut i, &mut j);
+            let s = quick_three_accum(&mut u, &mut v, t);
+            if s != 0.0 {
+                x[k] = s;
+                k += 1;
+            }
+        }
+
+        // 残りの項は最下位に足す
+        x[3] += a[i..].iter().sum::<Float>() + b[j..].iter().sum::<Float>();
+
+        Self(renorm([x[0], x[1], x[2], x[3], 0.0]))
+    }
+}
+
+impl Sub for QuadDouble {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+// O(eps^3)までの項を正確に，それ以下をf64で足す
+impl Mul for QuadDouble {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self.0, rhs.0);
+
+        let (p0, q0) = two_prod(a[0], b[0]);
+
+        let (p1, q1) = two_prod(a[0], b[1]);
+        let (p2, q2) = two_prod(a[1], b[0]);
+
+        let (p3, q3) = two_prod(a[0], b[2]);
+        let (p4, q4) = two_prod(a[1], b[1]);
+        let (p5, q5) = two_prod(a[2], b[0]);
+
+        let (p1, p2, q0) = three_sum(p1, p2, q0);
+
+        // (p2, q1, q2) + (p3, p4, p5)
+        let (p2, q1, q2) = three_sum(p2, q1, q2);
+        let (p3, p4, p5) = three_sum(p3, p4, p5);
+        let (s0, t0) = two_sum(p2, p3);
+        let (s1, t1) = two_sum(q1, p4);
+        let s2 = q2 + p5;
+        let (s1, t0) = two_sum(s1, t0);
+        let s2 = s2 + (t0 + t1);
+
+        let s1 = s1
+            + (a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0]
+                + q0 + q3 + q4 + q5);
+
+        Self(renorm([p0, p1, s0, s1, s2]))
+    }
+}
+
+// 商をf64で5回求めて余りを補正する
+impl Div for QuadDouble {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let mut q = [0.0; 5];
+        let mut r = self;
+        for qi in q.iter_mut() {
+            *qi = r.0[0] / rhs.0[0];
+            r = r - rhs * Self::from_f64(*qi);
+        }
+        Self(renorm(q))
+    }
+}
+
+impl Rem for QuadDouble {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        self - rhs * (self / rhs).trunc()
+    }
+}
+
+impl Neg for QuadDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.map(|x| -x))
+    }
+}
+
+impl Zero for QuadDouble {
+    fn zero() -> Self {
+        Self([0.0; 4])
+    }
+    fn is_zero(&self) -> bool {
+        self.0[0] == 0.0
+    }
+}
+
+impl One for QuadDouble {
+    fn one() -> Self {
+        Self([1.0, 0.0, 0.0, 0.0])
+    }
+}
+
+impl Num for QuadDouble {
+    type FromStrRadixErr = ParseRealError;
+
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseRealError> {
+        parse_decimal(src, radix)
+    }
+}
+
+impl Real for QuadDouble {
+    fn from_f64(x: Float) -> Self {
+        Self([x, 0.0, 0.0, 0.0])
+    }
+    fn to_f64(self) -> Float {
+        self.0[0] + self.0[1]
+    }
+    fn abs(self) -> Self {
+        if self.0[0] < 0.0 { -self } else { self }
+    }
+    fn signum(self) -> Self {
+        Self::from_f64(self.0[0].signum())
+    }
+    fn is_finite(self) -> bool {
+        self.0.iter().all(|x| x.is_finite())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn division_is_accurate_to_quad_double_precision() {
+        let one = QuadDouble::one();
+        let three = QuadDouble::from_f64(3.0);
+        let error = (one / three * three - one).parts()[0].abs();
+        assert!(error < 1e-62, "{}", error);
+        assert_ne!((one / three).parts()[3], 0.0);
+
+        let x = QuadDouble::new([1.0, 1e-20, 1e-40, 0.0]);
+        let y = QuadDouble::from_f64(7.0);
+        assert!((x / y * y - x).parts()[0].abs() < 1e-62);
+    }
+
+    #[test]
+    fn floor_and_trunc_of_negative_numbers() {
+        let qd = QuadDouble::from_f64;
+        assert_eq!(qd(-1.5).floor(), qd(-2.0));
+        assert_eq!(qd(-1.5).trunc(), qd(-1.0));
+        assert_eq!(qd(-2.0).trunc(), qd(-2.0));
+
+        // 下位の項の端数で結果が変わる
+        let above = QuadDouble::new([-2.0, 0.0, 1e-40, 0.0]);
+        assert_eq!(above.floor(), qd(-2.0));
+        assert_eq!(above.trunc(), qd(-1.0));
+        let below = QuadDouble::new([-2.0, 0.0, -1e-40, 0.0]);
+        assert_eq!(below.floor(), qd(-3.0));
+        assert_eq!(below.trunc(), qd(-2.0));
+    }
+
+    #[test]
+    fn parse_to_quad_double_precision() {
+        let third: QuadDouble = Num::from_str_radix("0.33333333333333333333333333333333333333333333333333333333333333333333", 10).unwrap();
+        let error = (third * QuadDouble::from_f64(3.0) - QuadDouble::one()).parts()[0].abs();
+        assert!(error < 1e-62, "{}", error);
+    }
+}
@@ -0,0 +1,530 @@
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::ops::Neg;
+
+use num::traits::{Num, Zero};
+use num_complex::{self, Complex};
+use crate::util::types::Float;
+
+// 反復計算に使う実数型．f32(高速なpreview)，f64(既定)，多倍長型(深いzoom)などを切り替えられる
+// 脱出判定や色付けに使う値はFloat(f64)に変換して扱う
+pub trait Real: Num + Neg<Output = Self> + PartialOrd + Copy + Send + Sync + Debug + 'static {
+    fn from_f64(x: Float) -> Self;
+    fn to_f64(self) -> Float;
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+    // オーバーフローやNaNになっていなければtrue
+    fn is_finite(self) -> bool;
+
+    // 超越関数．既定の実装は級数とNewton法で型の精度まで求める．f32, f64は標準ライブラリを使う
+    // 指数部の範囲はf64と同じとして扱う(exp(710)は無限大)
+    fn pi() -> Self {
+        from_chunks(&PI_CHUNKS)
+    }
+    fn exp(self) -> Self {
+        exp_series(self)
+    }
+    fn ln(self) -> Self {
+        ln_newton(self)
+    }
+    fn sin_cos(self) -> (Self, Self) {
+        sin_cos_series(self)
+    }
+    fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+    fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+    // selfをy座標とする偏角
+    fn atan2(self, x: Self) -> Self {
+        atan2_newton(self, x)
+    }
+}
+
+macro_rules! impl_std_transcendental {
+    ($t:ident) => {
+        fn pi() -> Self {
+            std::$t::consts::PI
+        }
+        fn exp(self) -> Self {
+            $t::exp(self)
+        }
+        fn ln(self) -> Self {
+            $t::ln(self)
+        }
+        fn sin_cos(self) -> (Self, Self) {
+            $t::sin_cos(self)
+        }
+        fn sin(self) -> Self {
+            $t::sin(self)
+        }
+        fn cos(self) -> Self {
+            $t::cos(self)
+        }
+        fn atan2(self, x: Self) -> Self {
+            $t::atan2(self, x)
+        }
+    };
+}
+
+impl Real for f32 {
+    fn from_f64(x: Float) -> Self {
+        x as f32
+    }
+    fn to_f64(self) -> Float {
+        self as Float
+    }
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+    fn signum(self) -> Self {
+        f32::signum(self)
+    }
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+    impl_std_transcendental!(f32);
+}
+
+impl Real for f64 {
+    fn from_f64(x: Float) -> Self {
+        x
+    }
+    fn to_f64(self) -> Float {
+        self
+    }
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+    fn signum(self) -> Self {
+        f64::signum(self)
+    }
+    fn is_finite(self) -> bool {
+        f64::is_finite(self)
+    }
+    impl_std_transcendental!(f64);
+}
+
+pub fn complex_from_f64<T: Real>(z: Complex<Float>) -> Complex<T> {
+    Complex::new(T::from_f64(z.re), T::from_f64(z.im))
+}
+
+pub fn complex_to_f64<T: Real>(z: Complex<T>) -> Complex<Float> {
+    Complex::new(z.re.to_f64(), z.im.to_f64())
+}
+
+// num_complexの超越関数はnum::Floatを要求するので，Realで同じものを用意する
+pub fn complex_exp<T: Real>(z: Complex<T>) -> Complex<T> {
+    let r = z.re.exp();
+    let (sin, cos) = z.im.sin_cos();
+    Complex::new(r * cos, r * sin)
+}
+
+// 主値．偏角は(-π, π]
+pub fn complex_ln<T: Real>(z: Complex<T>) -> Complex<T> {
+    Complex::new(z.norm_sqr().ln() * T::from_f64(0.5), z.im.atan2(z.re))
+}
+
+pub fn complex_sin<T: Real>(z: Complex<T>) -> Complex<T> {
+    let (sin, cos) = z.re.sin_cos();
+    let (sinh, cosh) = sinh_cosh(z.im);
+    Complex::new(sin * cosh, cos * sinh)
+}
+
+pub fn complex_cos<T: Real>(z: Complex<T>) -> Complex<T> {
+    let (sin, cos) = z.re.sin_cos();
+    let (sinh, cosh) = sinh_cosh(z.im);
+    Complex::new(cos * cosh, -(sin * sinh))
+}
+
+// z^p = exp(p ln z)．z = 0はln zが定義できないのでf64のnum_complexに合わせる
+pub fn complex_powf<T: Real>(z: Complex<T>, p: Float) -> Complex<T> {
+    if z.is_zero() {
+        return complex_from_f64(Complex::<Float>::ZERO.powf(p));
+    }
+    complex_exp(complex_ln(z) * T::from_f64(p))
+}
+
+
+/* ===== transcendental functions ===== */
+
+// 定数をf64で正確に表せる52bitずつの整数に分けたもの．x = Σ chunks[i] 2^(-52i)
+// BigFloat<16>の1024bitより細かいところまで持つ
+const PI_CHUNKS: [Float; 21] = [
+    3.0, 637676621945392.0, 2483878800010755.0, 1978246002414466.0,
+    608713665677358.0, 4408555304568980.0, 1451500945068051.0, 2106549496247118.0,
+    2546936018905527.0, 3544572316874829.0, 1607165069039506.0, 401723736170417.0,
+    3326233322887391.0, 3196018306264509.0, 29548162376111.0, 4176642690345895.0,
+    3536329793896345.0, 644423679162646.0, 3649313485021829.0, 2515413736264205.0,
+    2376419605324888.0,
+];
+const LN2_CHUNKS: [Float; 21] = [
+    0.0, 3121657384082679.0, 2722158387369987.0, 4274261058335794.0,
+    1814850077297165.0, 410911243215550.0, 2173142326700972.0, 2679893518169263.0,
+    2844509279792693.0, 3399149127103859.0, 3141379508675902.0, 2652715182501516.0,
+    1436837722796412.0, 993870979120187.0, 341261913780128.0, 4159888417929004.0,
+    4089846593571018.0, 4325454782890755.0, 642550186739503.0, 578592989960572.0,
+    1424141727434740.0,
+];
+
+// 級数の項数とNewton法の反復回数の上限．Newton法は1回で精度がほぼ倍になるので，f64の初期値から6回で1024bitを超える
+const MAX_TERMS: usize = 1000;
+const MAX_NEWTON: usize = 6;
+// expの引数を2^EXP_HALVINGSで割ってから級数を使う
+const EXP_HALVINGS: i32 = 10;
+
+// Horner法で下位のchunkから足す．途中の値は2^52程度に収まる
+fn from_chunks<T: Real>(chunks: &[Float]) -> T {
+    let scale = T::from_f64((2.0 as Float).powi(-52));
+    chunks
+        .iter()
+        .rev()
+        .fold(T::zero(), |acc, &c| acc * scale + T::from_f64(c))
+}
+
+// x = k ln2 + rとし，e^(r / 2^m) - 1を級数で求めてから
+// (1 + s)^2 - 1 = s(2 + s)でm回2乗する．1 + sのまま2乗するより丸め誤差が増えない
+fn exp_series<T: Real>(x: T) -> T {
+    let xf = x.to_f64();
+    let approx = xf.exp();
+    if !x.is_finite() || approx == 0.0 || !approx.is_finite() {
+        return T::from_f64(approx);
+    }
+
+    let k = (xf / std::f64::consts::LN_2).round();
+    let r = (x - from_chunks::<T>(&LN2_CHUNKS) * T::from_f64(k)) * T::from_f64((2.0 as Float).powi(-EXP_HALVINGS));
+
+    let mut term = r;
+    let mut s = r;
+    for n in 2..MAX_TERMS {
+        term = term * r / T::from_f64(n as Float);
+        let next = s + term;
+        if next == s {
+            break;
+        }
+        s = next;
+    }
+    let two = T::from_f64(2.0);
+    for _ in 0..EXP_HALVINGS {
+        s = s * (two + s);
+    }
+
+    // 2^kはf64の範囲を超えうるので半分ずつ掛ける
+    let n = k.abs() as u32;
+    let (a, b) = (powu(two, n / 2), powu(two, n - n / 2));
+    let y = T::one() + s;
+    if k >= 0.0 { y * a * b } else { y / a / b }
+}
+
+// Newton法 y <- y + x e^(-y) - 1
+fn ln_newton<T: Real>(x: T) -> T {
+    let approx = x.to_f64().ln();
+    if !x.is_finite() || !approx.is_finite() {
+        return T::from_f64(approx);
+    }
+
+    let mut y = T::from_f64(approx);
+    for _ in 0..MAX_NEWTON {
+        let next = y + x * (-y).exp() - T::one();
+        if next == y {
+            break;
+        }
+        y = next;
+    }
+    y
+}
+
+// x = k π/2 + r (|r| <= π/4)として，sin r, cos rを級数で求める
+// |x|が大きいとkをf64に丸めた誤差でrが残るので，|r| <= π/4になるまでT上で引き直す
+fn sin_cos_series<T: Real>(x: T) -> (T, T) {
+    if !x.is_finite() {
+        let xf = x.to_f64();
+        return (T::from_f64(xf.sin()), T::from_f64(xf.cos()));
+    }
+
+    let half_pi = T::pi() * T::from_f64(0.5);
+    let mut r = x;
+    let mut quadrant = 0.0;  // k mod 4
+    loop {
+        let q = (r / half_pi).to_f64();
+        if q.abs() <= 0.5 {
+            break;
+        }
+        let k = q.round();
+        r = r - half_pi * T::from_f64(k);
+        quadrant = (quadrant + k.rem_euclid(4.0)) % 4.0;
+    }
+
+    let r2 = r * r;
+    let (mut sin, mut cos) = (r, T::one());
+    let (mut sin_term, mut cos_term) = (r, T::one());
+    for n in 1..MAX_TERMS {
+        let n = n as Float;
+        sin_term = -(sin_term * r2 / T::from_f64(2.0 * n * (2.0 * n + 1.0)));
+        cos_term = -(cos_term * r2 / T::from_f64((2.0 * n - 1.0) * 2.0 * n));
+        let (sin_next, cos_next) = (sin + sin_term, cos + cos_term);
+        if sin_next == sin && cos_next == cos {
+            break;
+        }
+        (sin, cos) = (sin_next, cos_next);
+    }
+
+    match quadrant as i64 {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    }
+}
+
+// f(θ) = y cos θ - x sin θ = 0をNewton法で解く
+fn atan2_newton<T: Real>(y: T, x: T) -> T {
+    let mut theta = T::from_f64(y.to_f64().atan2(x.to_f64()));
+    if !(x.is_finite() && y.is_finite()) || (x.is_zero() && y.is_zero()) {
+        return theta;
+    }
+
+    for _ in 0..MAX_NEWTON {
+        let (sin, cos) = theta.sin_cos();
+        let next = theta + (y * cos - x * sin) / (x * cos + y * sin);
+        if next == theta {
+            break;
+        }
+        theta = next;
+    }
+    theta
+}
+
+// |x| < 1では差の桁落ちを避けるため級数で求める
+fn sinh_cosh<T: Real>(x: T) -> (T, T) {
+    if x.abs().to_f64() >= 1.0 {
+        let e = x.exp();
+        let inv = T::one() / e;
+        let half = T::from_f64(0.5);
+        return ((e - inv) * half, (e + inv) * half);
+    }
+
+    let x2 = x * x;
+    let (mut sinh, mut cosh) = (x, T::one());
+    let (mut sinh_term, mut cosh_term) = (x, T::one());
+    for n in 1..MAX_TERMS {
+        let n = n as Float;
+        sinh_term = sinh_term * x2 / T::from_f64(2.0 * n * (2.0 * n + 1.0));
+        cosh_term = cosh_term * x2 / T::from_f64((2.0 * n - 1.0) * 2.0 * n);
+        let (sinh_next, cosh_next) = (sinh + sinh_term, cosh + cosh_term);
+        if sinh_next == sinh && cosh_next == cosh {
+            break;
+        }
+        (sinh, cosh) = (sinh_next, cosh_next);
+    }
+    (sinh, cosh)
+}
+
+
+// 多倍長型のfrom_str_radixで返すエラー．10進表記以外や，型で表せないほど大きな数は読めない
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseRealError;
+
+impl fmt::Display for ParseRealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number")
+    }
+}
+
+impl Error for ParseRealError {}
+
+// 10進表記(符号，小数点，指数部)をTの精度で読む
+pub(crate) fn parse_decimal<T: Real>(src: &str, radix: u32) -> Result<T, ParseRealError> {
+    if radix != 10 {
+        return Err(ParseRealError);
+    }
+
+    let src = src.trim();
+    let (negative, body) = match src.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, src.strip_prefix('+').unwrap_or(src)),
+    };
+    let (mantissa, mut exp) = match body.find(['e', 'E']) {
+        Some(i) => (&body[..i], body[i + 1..].parse::<i32>().map_err(|_| ParseRealError)?),
+        None => (body, 0),
+    };
+
+    let ten = T::from_f64(10.0);
+    let mut value = T::zero();
+    let mut digits = 0;
+    let mut seen_point = false;
+    for ch in mantissa.chars() {
+        match ch {
+            '0'..='9' => {
+                value = value * ten + T::from_f64((ch as u8 - b'0') as Float);
+                digits += 1;
+                if seen_point {
+                    exp = exp.checked_sub(1).ok_or(ParseRealError)?;
+                }
+            }
+            '.' if !seen_point => seen_point = true,
+            _ => return Err(ParseRealError),
+        }
+    }
+    if digits == 0 {
+        return Err(ParseRealError);
+    }
+
+    if value.is_zero() {
+        return Ok(value);
+    }
+
+    // 指数の範囲は型ごとに違うので，10^|exp|を求めてから範囲外かを調べる
+    let scale = powu(T::from_f64(10.0), exp.unsigned_abs());
+    let value = if exp >= 0 {
+        value * scale
+    } else if scale.is_finite() {
+        value / scale
+    } else {
+        // 10^|exp|が表せない型では，f64と同じく0にアンダーフローさせる
+        T::zero()
+    };
+    if !value.is_finite() {
+        return Err(ParseRealError);
+    }
+    Ok(if negative { -value } else { value })
+}
+
+// base^nを2進展開で求める．途中で必要以上に大きな冪は作らない
+fn powu<T: Real>(mut base: T, mut n: u32) -> T {
+    let mut result = T::one();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result * base;
+        }
+        n >>= 1;
+        if n > 0 {
+            base = base * base;
+        }
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::traits::One;
+    use crate::util::big_float::BigFloat;
+    use crate::util::double_double::DoubleDouble;
+    use crate::util::quad_double::QuadDouble;
+
+    #[test]
+    fn parse_decimal_notation() {
+        let parse = |s| parse_decimal::<DoubleDouble>(s, 10);
+        assert_eq!(parse("1.5"), Ok(DoubleDouble::from_f64(1.5)));
+        assert_eq!(parse("-1.5e3"), Ok(DoubleDouble::from_f64(-1500.0)));
+        assert_eq!(parse("+.25"), Ok(DoubleDouble::from_f64(0.25)));
+        assert_eq!(parse(" 12E-1 "), Ok(DoubleDouble::from_f64(12.0) / DoubleDouble::from_f64(10.0)));
+        // 0.1はf64より細かく表せる
+        let tenth = parse("0.1").unwrap();
+        assert_eq!(tenth, DoubleDouble::one() / DoubleDouble::from_f64(10.0));
+        assert_ne!(tenth.lo(), 0.0);
+
+        for src in ["", "-", "1.2.3", "1e", "1e3.5", "abc", "0x10"] {
+            assert_eq!(parse(src), Err(ParseRealError), "{:?}", src);
+        }
+        assert_eq!(parse_decimal::<DoubleDouble>("10", 16), Err(ParseRealError));
+    }
+
+    #[test]
+    fn parse_decimal_exponent_range() {
+        // double-doubleの範囲を超える数はエラー，小さすぎる数は0
+        assert_eq!(parse_decimal::<DoubleDouble>("1e400", 10), Err(ParseRealError));
+        assert_eq!(parse_decimal::<DoubleDouble>("1e-400", 10), Ok(DoubleDouble::zero()));
+        assert_eq!(parse_decimal::<QuadDouble>("-2e999999", 10), Err(ParseRealError));
+        assert_eq!(parse_decimal::<QuadDouble>("0e999999", 10), Ok(QuadDouble::zero()));
+        assert_eq!(parse_decimal::<DoubleDouble>("1e99999999999", 10), Err(ParseRealError));
+
+        // BigFloatはf64の範囲を超える指数もそのまま読める
+        let big = |s| parse_decimal::<BigFloat<4>>(s, 10).unwrap();
+        let ratio = big("3e500") / big("1e499");
+        assert!((ratio.to_f64() - 30.0).abs() < 1e-12);
+        let product = big("2e-1000") * big("5e999");
+        assert!((product.to_f64() - 1.0).abs() < 1e-12);
+        assert!(parse_decimal::<BigFloat<2>>("1e100000", 10).is_ok());
+    }
+
+    const E: &str = "2.7182818284590452353602874713526624977572470936999595749669676277240766303535475945713821785251664274274663919320030599218174135966290435729003342952605956307381";
+
+    fn assert_close<T: Real>(a: T, b: T, tolerance: Float) {
+        let error = ((a - b) / b).abs().to_f64();
+        assert!(error < tolerance, "relative error {}", error);
+    }
+
+    // 恒等式や既知の値で，型の精度まで合っているかを調べる
+    fn check_transcendental<T: Real>(tolerance: Float) {
+        let t = T::from_f64;
+        let e = parse_decimal::<T>(E, 10).unwrap();
+        assert_close(t(1.0).exp(), e, tolerance);
+        assert_close(e.ln(), t(1.0), tolerance);
+        for x in [-30.0, -2.5, -0.5, 0.3, 7.0, 100.0] {
+            assert_close(t(x).exp().ln(), t(x), tolerance);
+            assert_close(t(x).exp() * (-t(x)).exp(), t(1.0), tolerance);
+        }
+
+        let pi = T::pi();
+        assert_close((pi / t(6.0)).sin(), t(0.5), tolerance);
+        assert_close((pi / t(3.0)).cos(), t(0.5), tolerance);
+        assert_close(t(1.0).atan2(t(1.0)) * t(4.0), pi, tolerance);
+        assert_close(t(-1.0).atan2(t(-1.0)) * t(-4.0), pi * t(3.0), tolerance);
+        for x in [-40.0, -3.0, 0.1, 1.0, 2.0, 5.5] {
+            let (sin, cos) = t(x).sin_cos();
+            assert_close(sin * sin + cos * cos, t(1.0), tolerance);
+            assert_close(sin.atan2(cos), t(x) - pi * t((x / std::f64::consts::TAU).round() * 2.0), tolerance);
+        }
+    }
+
+    #[test]
+    fn transcendental_functions_reach_type_precision() {
+        check_transcendental::<f64>(1e-13);
+        check_transcendental::<DoubleDouble>(1e-29);
+        check_transcendental::<QuadDouble>(1e-60);
+        check_transcendental::<BigFloat<4>>(1e-72);
+        check_transcendental::<BigFloat<8>>(1e-145);
+    }
+
+    // 大きな引数でもNaNにならず，f64の値(引数はf64で正確に表せる)に近い
+    // πの精度が有限なので，誤差は|x|に比例して大きくなる
+    fn check_large_sin_cos<T: Real>() {
+        for x in [1e19, -5e21, 3.5e17, 1e300] {
+            let (sin, cos) = T::from_f64(x).sin_cos();
+            assert!(sin.is_finite() && cos.is_finite(), "{}: {:?}", x, (sin, cos));
+            if x.abs() < 1e22 {
+                assert!((sin.to_f64() - x.sin()).abs() < 1e-9, "sin {}: {:?} != {}", x, sin, x.sin());
+                assert!((cos.to_f64() - x.cos()).abs() < 1e-9, "cos {}: {:?} != {}", x, cos, x.cos());
+            }
+        }
+    }
+
+    #[test]
+    fn sin_cos_of_large_arguments() {
+        check_large_sin_cos::<DoubleDouble>();
+        check_large_sin_cos::<QuadDouble>();
+        check_large_sin_cos::<BigFloat<4>>();
+    }
+
+    #[test]
+    fn complex_functions_match_num_complex() {
+        for z in [Complex::new(0.5, -1.5), Complex::new(-2.0, 0.25), Complex::new(3.0, 4.0)] {
+            let dd = complex_from_f64::<DoubleDouble>(z);
+            let pairs = [
+                (complex_exp(dd), z.exp()),
+                (complex_ln(dd), z.ln()),
+                (complex_sin(dd), z.sin()),
+                (complex_cos(dd), z.cos()),
+                (complex_powf(dd, 2.5), z.powf(2.5)),
+            ];
+            for (a, b) in pairs {
+                assert!((complex_to_f64(a) - b).norm() < 1e-13 * b.norm(), "{} != {}", complex_to_f64(a), b);
+            }
+        }
+        assert_eq!(complex_powf(Complex::<DoubleDouble>::zero(), 2.5), Complex::zero());
+    }
+}